@@ -1,125 +1,481 @@
 use std::{
-    collections::{HashMap, HashSet},
-    env,
-    io::stdout,
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, stdout, Write},
+    path::PathBuf,
     time::{Duration, Instant},
 };
 
 use anyhow::Error;
+use clap::ArgEnum;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute, terminal,
 };
-use futures::{stream, StreamExt};
+use futures::{stream::FuturesUnordered, StreamExt};
+use node_semver::Version;
 use reqwest::Client;
 use tokio::sync::mpsc;
 use tui::{backend::CrosstermBackend, widgets::TableState, Terminal};
 
-use crate::{args::Args, project::Project, registry, ui::draw_ui};
+use crate::{
+    args::{Args, UnionRanges},
+    cache::DiskCache,
+    config::{Config, SeverityColors},
+    dependency::{Dependency, FetchedPackage, Selection},
+    github::{self, Release},
+    lockfile::Lockfile,
+    project::Project,
+    registry::{self, FetchLimits, Metadata, Registries},
+    text,
+    ui::draw_ui,
+    update::{self, VersionChange},
+    version::{self, UpdatePolicy, VersionSection},
+};
 
 #[derive(Debug)]
 enum ApplicationEvent<T> {
     Input(T),
+    Resize,
     Tick,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum DependencyTable {
     Runtime,
     Dev,
+    Peer,
+    /// Dependencies that may fail to install, e.g. platform-specific ones.
+    Optional,
+    /// Versions pinned by npm `overrides`, at any depth.
+    Overrides,
+}
+
+impl DependencyTable {
+    /// Every table, in the order they are shown.
+    pub const ALL: [DependencyTable; 5] = [
+        DependencyTable::Runtime,
+        DependencyTable::Dev,
+        DependencyTable::Peer,
+        DependencyTable::Optional,
+        DependencyTable::Overrides,
+    ];
+
+    /// Field of `package.json` that lists the dependencies of the table.
+    pub fn key(&self) -> &'static str {
+        match self {
+            DependencyTable::Runtime => "dependencies",
+            DependencyTable::Dev => "devDependencies",
+            DependencyTable::Peer => "peerDependencies",
+            DependencyTable::Optional => "optionalDependencies",
+            DependencyTable::Overrides => "overrides",
+        }
+    }
+}
+
+/// Order in which dependencies are listed in the tables.
+#[derive(ArgEnum, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SortMode {
+    /// Keep the order of `package.json`.
+    Manifest,
+    /// Most severe updates first, packages without a known update last.
+    Severity,
+    /// Packages whose latest version was published most recently first.
+    #[clap(name = "latest-desc")]
+    LatestPublished,
+}
+
+impl SortMode {
+    fn next(self) -> Self {
+        match self {
+            SortMode::Manifest => SortMode::Severity,
+            SortMode::Severity => SortMode::LatestPublished,
+            SortMode::LatestPublished => SortMode::Manifest,
+        }
+    }
+}
+
+/// Version columns shown next to the package names.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum VisibleColumns {
+    Current,
+    CurrentAndLatest,
+    /// Also show the newest version allowed by the declared range.
+    CurrentWantedAndLatest,
+    /// Also show whether the declared range allows the latest version.
+    CurrentWantedLatestAndRange,
+}
+
+impl VisibleColumns {
+    fn next(self) -> Self {
+        match self {
+            VisibleColumns::Current => VisibleColumns::CurrentAndLatest,
+            VisibleColumns::CurrentAndLatest => VisibleColumns::CurrentWantedAndLatest,
+            VisibleColumns::CurrentWantedAndLatest => VisibleColumns::CurrentWantedLatestAndRange,
+            VisibleColumns::CurrentWantedLatestAndRange => VisibleColumns::Current,
+        }
+    }
+
+    pub fn shows_wanted(self) -> bool {
+        matches!(
+            self,
+            VisibleColumns::CurrentWantedAndLatest | VisibleColumns::CurrentWantedLatestAndRange
+        )
+    }
+
+    pub fn shows_latest(self) -> bool {
+        self != VisibleColumns::Current
+    }
+
+    pub fn shows_range(self) -> bool {
+        self == VisibleColumns::CurrentWantedLatestAndRange
+    }
+}
+
+/// Selection, scrolling and marks of a dependency table.
+#[derive(Default)]
+pub struct TableView {
+    pub len: usize,
+    pub table_state: TableState,
+    /// Index of the first row that the table shows, only rows from there on are rendered.
+    pub offset: usize,
+    /// Keys of the dependencies marked for update, see `Dependency::key`.
+    pub update_index: HashSet<String>,
 }
 
 pub struct State {
-    pub dependencies_len: usize,
-    pub dev_dependencies_len: usize,
+    /// Number of packages to fetch from the registry.
+    pub fetch_len: usize,
     pub active_table: DependencyTable,
-    pub dependencies_table_state: TableState,
-    pub dev_dependencies_table_state: TableState,
-    pub update_index: HashSet<usize>,
-    pub dev_update_index: HashSet<usize>,
+    pub sort_mode: SortMode,
+    pub visible_columns: VisibleColumns,
+    /// Only dependencies with an update at least this severe are shown, if set.
+    pub min_severity: Option<VersionSection>,
+    /// Notes about packages from the `[notes]` of `packrat.toml`, shown after their names.
+    pub notes: HashMap<String, String>,
+    /// Whether the UI is drawn with ASCII characters only, see `--ascii`.
+    pub ascii: bool,
+    /// Color of the updates of each severity, from the `[severity]` of `packrat.toml`.
+    pub severity_colors: SeverityColors,
+    /// View of each table, in the order of `DependencyTable::ALL`.
+    pub tables: [TableView; 5],
+    /// Packages that pull in the selected package, shown in a popup while set.
+    pub dependents: Option<Dependents>,
+    /// Releases of the selected package since the declared version, shown in a popup while set.
+    pub release_notes: Option<ReleaseNotes>,
+    /// Changes of the marked updates that wait for confirmation, shown in a popup while set.
+    pub confirmation: Option<Confirmation>,
+    pub fetch_started: Instant,
+    /// When each fetch completed within the last `THROUGHPUT_WINDOW`.
+    pub fetch_completions: VecDeque<Instant>,
+    /// Packages whose metadata could not be fetched.
+    pub fetch_errors: Vec<(String, registry::Error)>,
+    /// Packages that may be missing from the registry, see `Project::is_optional`, so that failing to fetch them is no
+    /// error.
+    pub optional_packages: HashSet<String>,
+    /// Whether the packages that could not be fetched are listed in a popup.
+    pub shows_fetch_errors: bool,
+    /// Result of the last action, shown in place of the help text for a while.
+    pub toast: Option<Toast>,
+}
+
+/// Message shown in the footer for `TOAST_DURATION`.
+pub struct Toast {
+    pub message: String,
+    pub is_error: bool,
+    shown_at: Instant,
+}
+
+/// How long a toast stays visible.
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// Period over which the fetch throughput is averaged.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(2);
+
+impl State {
+    /// State with the first row of each non-empty table selected, and the first non-empty table active.
+    pub fn new(table_len: impl Fn(DependencyTable) -> usize, fetch_len: usize) -> Self {
+        let mut state = State {
+            fetch_len,
+
+            active_table: DependencyTable::Runtime,
+            sort_mode: SortMode::Manifest,
+            visible_columns: VisibleColumns::CurrentAndLatest,
+            min_severity: None,
+            notes: HashMap::new(),
+            ascii: false,
+            severity_colors: SeverityColors::default(),
+            tables: Default::default(),
+
+            dependents: None,
+            release_notes: None,
+            confirmation: None,
+
+            fetch_started: Instant::now(),
+            fetch_completions: VecDeque::new(),
+            fetch_errors: Vec::new(),
+            optional_packages: HashSet::new(),
+            shows_fetch_errors: false,
+            toast: None,
+        };
+
+        for table in DependencyTable::ALL.into_iter().rev() {
+            let view = state.table_mut(table);
+            view.len = table_len(table);
+            if view.len != 0 {
+                view.table_state.select(Some(0));
+                state.active_table = table;
+            }
+        }
+
+        state
+    }
+
+    pub fn table(&self, table: DependencyTable) -> &TableView {
+        &self.tables[table as usize]
+    }
+
+    pub fn table_mut(&mut self, table: DependencyTable) -> &mut TableView {
+        &mut self.tables[table as usize]
+    }
+
+    /// Keys of the dependencies marked for update in the given table.
+    pub fn update_index(&self, table: DependencyTable) -> &HashSet<String> {
+        &self.table(table).update_index
+    }
+
+    /// Number of packages that could not be fetched, except optional ones.
+    pub fn error_count(&self) -> usize {
+        self.fetch_errors
+            .iter()
+            .filter(|(name, _)| !self.optional_packages.contains(name))
+            .count()
+    }
+
+    /// Whether the dependency passes the `min_severity` filter.
+    pub fn shows(&self, dependency: &Dependency) -> bool {
+        match self.min_severity {
+            Some(min_severity) => dependency
+                .severity
+                .is_some_and(|severity| severity <= min_severity),
+            None => true,
+        }
+    }
+
+    /// Tables that list any dependencies, which are the ones shown.
+    pub fn visible_tables(&self) -> impl Iterator<Item = DependencyTable> + '_ {
+        DependencyTable::ALL
+            .into_iter()
+            .filter(|&table| self.table(table).len != 0)
+    }
+
+    /// Fetched packages per second, averaged over the last `THROUGHPUT_WINDOW`.
+    pub fn throughput(&self) -> f64 {
+        let window = THROUGHPUT_WINDOW.min(self.fetch_started.elapsed());
+        if window.is_zero() {
+            return 0.0;
+        }
+
+        self.fetch_completions.len() as f64 / window.as_secs_f64()
+    }
+
+    fn record_fetch_completion(&mut self) {
+        self.fetch_completions.push_back(Instant::now());
+    }
+
+    fn show_toast(&mut self, message: String, is_error: bool) {
+        self.toast = Some(Toast {
+            message,
+            is_error,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Hide the toast once it has been shown for `TOAST_DURATION`, returning whether it was hidden.
+    fn prune_toast(&mut self) -> bool {
+        let is_expired = self
+            .toast
+            .as_ref()
+            .is_some_and(|toast| toast.shown_at.elapsed() >= TOAST_DURATION);
+        if is_expired {
+            self.toast = None;
+        }
+
+        is_expired
+    }
+
+    /// Forget fetch completions that fell out of the throughput window, returning whether there were any.
+    fn prune_fetch_completions(&mut self) -> bool {
+        let len = self.fetch_completions.len();
+        while let Some(completion) = self.fetch_completions.front() {
+            if completion.elapsed() <= THROUGHPUT_WINDOW {
+                break;
+            }
+            self.fetch_completions.pop_front();
+        }
+
+        self.fetch_completions.len() != len
+    }
+}
+
+/// Changes to `package.json` that are written once confirmed.
+pub struct Confirmation {
+    pub changes: Vec<VersionChange>,
+    pub after_update: AfterUpdate,
+}
+
+/// What happens once the confirmed changes are written.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum AfterUpdate {
+    Stay,
+    Close,
+    /// Fetch the updated packages again, to check that they are up to date now or show any newer release.
+    FetchAgain,
+}
+
+/// Reverse dependency lookup of a package in `package-lock.json`.
+pub struct Dependents {
+    pub name: String,
+    /// `None` if the project has no readable lockfile.
+    pub lookup: Option<DependentsLookup>,
+}
+
+pub struct DependentsLookup {
+    /// Packages that directly depend on the package.
+    pub dependents: Vec<String>,
+    /// Direct dependencies of the project that pull the package in.
+    pub direct_dependents: Vec<String>,
+}
+
+/// GitHub releases of a package between its declared and latest version.
+pub struct ReleaseNotes {
+    pub name: String,
+    /// `None` while the releases are being fetched.
+    pub releases: Option<Result<Vec<Release>, String>>,
+}
+
+fn to_owned_names(names: Vec<&str>) -> Vec<String> {
+    names.into_iter().map(str::to_owned).collect()
 }
 
 pub struct Application {
     project: Project,
-    pub fetched_packages: HashMap<String, String>,
+    selection: Selection,
+    lockfile: Option<Lockfile>,
+    registries: Registries,
+    /// Client for registry requests, built up front so that an invalid `--ca-cert` stops packrat before the UI opens.
+    client: Client,
+    /// Client for the GitHub API, kept apart from `client` so that `--insecure` never exposes the token.
+    github_client: Client,
+    /// Token to fetch release notes from the GitHub API with, see `--github-token`.
+    github_token: Option<String>,
+    /// File that records every applied update.
+    log_file: Option<PathBuf>,
+    update_policy: UpdatePolicy,
+    union_ranges: UnionRanges,
+    /// Whether marking every update with `a` skips development dependencies, see `--no-bulk-dev`.
+    no_bulk_dev: bool,
+    /// Most severe update that marking every update with `a` includes, see `--update-up-to`.
+    update_up_to: Option<VersionSection>,
+    /// Shown when asking to confirm an update if `package.json` had uncommitted changes when packrat started.
+    uncommitted_changes_warning: Option<String>,
+    /// Where fetched packages are persisted as they arrive, so that an interrupted run can resume.
+    disk_cache: Option<DiskCache>,
+    /// When fetching gives up on the remaining packages and how fast it goes, see `--timeout-total` and `--batch-size`.
+    fetch_limits: FetchLimits,
+    /// Version that each fetched package should be updated to.
+    pub fetched_packages: HashMap<String, FetchedPackage>,
     state: State,
+    /// Severity that the filter toggled with `f` shows at least.
+    severity_filter: VersionSection,
+    /// Pull request description to print once the terminal is restored.
+    pull_request_body: Option<String>,
 }
 
 impl Application {
     pub fn new(args: Args) -> Result<Self, Error> {
-        let mut path = env::current_dir()?;
-        if let Some(custom_directory) = args.directory {
-            path = custom_directory;
-        }
-        path.push("package.json");
-
+        let path = args.manifest_path()?;
         let project = Project::new(&path)?;
+        let update_policy = UpdatePolicy::new(&args, &project, &path)?;
+        let selection = Selection::new(&args, &project)?;
+        let directory = path
+            .parent()
+            .expect("package.json path has a parent directory");
+        let lockfile = Lockfile::load(directory, &project);
+        let registries = args.registries(&project);
+        // Checked once, since the updates written by packrat itself are uncommitted changes too
+        let uncommitted_changes_warning = update::uncommitted_changes_warning(&args, &project);
 
-        let dependencies_len = match project.dependencies() {
-            Some(dependencies) => dependencies.len(),
-            None => 0,
-        };
-        let dev_dependencies_len = match project.dev_dependencies() {
-            Some(dependencies) => dependencies.len(),
-            None => 0,
-        };
+        let table_len = |table| selection.dependencies(&project, table).count();
+        let mut state = State::new(table_len, selection.names(&project).count());
+        state.min_severity = args.min_severity;
+        state.sort_mode = args.sort;
+        state.ascii = args.ascii;
+        state.optional_packages = selection
+            .names(&project)
+            .filter(|name| project.is_optional(name))
+            .cloned()
+            .collect();
+        let config = Config::load(directory)?;
+        state.notes = config.notes;
+        state.severity_colors = config.severity;
+        let mut warnings = project.warnings();
+        if args.insecure {
+            warnings.push(registry::INSECURE_WARNING.to_owned());
+        }
+        if !warnings.is_empty() {
+            state.show_toast(warnings.join("; "), true);
+        }
 
-        let mut app = Self {
+        let mut application = Self {
             project,
+            selection,
+            lockfile,
+            registries: registries.clone(),
+            client: args.client()?,
+            github_client: github::client(args.user_agent())?,
+            github_token: args.github_token.clone(),
+            log_file: args.log_file.clone(),
+            update_policy,
+            union_ranges: args.union_ranges,
+            no_bulk_dev: args.no_bulk_dev,
+            update_up_to: args.update_up_to,
+            uncommitted_changes_warning,
+            disk_cache: DiskCache::new(&args, registries),
+            fetch_limits: args.fetch_limits(),
 
             fetched_packages: HashMap::new(),
 
-            state: State {
-                dependencies_len,
-                dev_dependencies_len,
+            state,
 
-                active_table: DependencyTable::Runtime,
-                dependencies_table_state: TableState::default(),
-                dev_dependencies_table_state: TableState::default(),
+            severity_filter: args.min_severity.unwrap_or(VersionSection::Minor),
 
-                update_index: HashSet::new(),
-                dev_update_index: HashSet::new(),
-            },
+            pull_request_body: None,
         };
+        application.refresh_lengths();
 
-        if app.state.dev_dependencies_len != 0 {
-            app.state.dev_dependencies_table_state.select(Some(0));
-            app.state.active_table = DependencyTable::Dev;
-        }
-        if app.state.dependencies_len != 0 {
-            app.state.dependencies_table_state.select(Some(0));
-            app.state.active_table = DependencyTable::Runtime;
-        }
-
-        Ok(app)
+        Ok(application)
     }
 
+    /// Activate the next table that lists any dependencies.
     fn switch_table(&mut self) {
-        if self.state.dependencies_len == 0 || self.state.dev_dependencies_len == 0 {
-            return;
-        }
+        let visible_tables: Vec<_> = self.state.visible_tables().collect();
+        let active_index = visible_tables
+            .iter()
+            .position(|&table| table == self.state.active_table);
 
-        match self.state.active_table {
-            DependencyTable::Runtime => self.state.active_table = DependencyTable::Dev,
-            DependencyTable::Dev => self.state.active_table = DependencyTable::Runtime,
+        if let Some(active_index) = active_index {
+            self.state.active_table = visible_tables[(active_index + 1) % visible_tables.len()];
         }
     }
 
     fn next(&mut self) {
-        let (state, len) = match self.state.active_table {
-            DependencyTable::Runtime => (
-                &mut self.state.dependencies_table_state,
-                self.state.dependencies_len,
-            ),
-            DependencyTable::Dev => (
-                &mut self.state.dev_dependencies_table_state,
-                self.state.dev_dependencies_len,
-            ),
-        };
-
-        let i = match state.selected() {
+        let view = self.state.table_mut(self.state.active_table);
+        if view.len == 0 {
+            return;
+        }
+        let i = match view.table_state.selected() {
             Some(i) => {
-                if i >= len - 1 {
+                if i >= view.len - 1 {
                     0
                 } else {
                     i + 1
@@ -128,25 +484,18 @@ impl Application {
             None => 0,
         };
 
-        state.select(Some(i))
+        view.table_state.select(Some(i))
     }
 
     fn previous(&mut self) {
-        let (state, len) = match self.state.active_table {
-            DependencyTable::Runtime => (
-                &mut self.state.dependencies_table_state,
-                self.state.dependencies_len,
-            ),
-            DependencyTable::Dev => (
-                &mut self.state.dev_dependencies_table_state,
-                self.state.dev_dependencies_len,
-            ),
-        };
-
-        let i = match state.selected() {
+        let view = self.state.table_mut(self.state.active_table);
+        if view.len == 0 {
+            return;
+        }
+        let i = match view.table_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    len - 1
+                    view.len - 1
                 } else {
                     i - 1
                 }
@@ -154,88 +503,450 @@ impl Application {
             None => 0,
         };
 
-        state.select(Some(i))
+        view.table_state.select(Some(i))
     }
 
-    fn toggle_update(&mut self) {
-        match self.state.active_table {
-            DependencyTable::Runtime => {
-                let selected_index = self.state.dependencies_table_state.selected().unwrap_or(0);
-                if self.state.update_index.contains(&selected_index) {
-                    self.state.update_index.remove(&selected_index);
-                } else {
-                    self.state.update_index.insert(selected_index);
-                }
-            }
-            DependencyTable::Dev => {
-                let selected_index = self
-                    .state
-                    .dev_dependencies_table_state
-                    .selected()
-                    .unwrap_or(0);
-                if self.state.dev_update_index.contains(&selected_index) {
-                    self.state.dev_update_index.remove(&selected_index);
-                } else {
-                    self.state.dev_update_index.insert(selected_index);
-                }
+    /// Select the next package with an available update in the active table, or the previous one if `backwards`.
+    ///
+    /// Wraps around the ends of the table and keeps the selection if no package has an update.
+    fn jump_to_update(&mut self, backwards: bool) {
+        let table = self.state.active_table;
+        let dependencies = self.dependencies(table);
+        let len = dependencies.len();
+        if len == 0 {
+            return;
+        }
+
+        let selected = self.state.table(table).table_state.selected().unwrap_or(0);
+
+        let found = (1..=len)
+            .map(|step| match backwards {
+                true => (selected + len - step) % len,
+                false => (selected + step) % len,
+            })
+            .find(|&i| dependencies[i].severity.is_some());
+
+        if let Some(i) = found {
+            self.state.table_mut(table).table_state.select(Some(i));
+        }
+    }
+
+    /// Dependencies of a table in the order they are displayed.
+    fn dependencies(&self, table: DependencyTable) -> Vec<Dependency<'_>> {
+        let mut dependencies = Dependency::list(
+            &self.project,
+            &self.selection,
+            &self.fetched_packages,
+            table,
+            self.state.sort_mode,
+            self.state.update_index(table),
+        );
+        dependencies.retain(|dependency| self.state.shows(dependency));
+
+        dependencies
+    }
+
+    /// Count the rows of every table again after the filtered rows changed, keeping selections within the tables.
+    fn refresh_lengths(&mut self) {
+        for table in DependencyTable::ALL {
+            let len = self.dependencies(table).len();
+            let view = self.state.table_mut(table);
+            view.len = len;
+            let selected = match view.table_state.selected() {
+                _ if len == 0 => None,
+                Some(selected) => Some(selected.min(len - 1)),
+                None => Some(0),
+            };
+            view.table_state.select(selected);
+        }
+
+        let first_visible = self.state.visible_tables().next();
+        if let (0, Some(table)) = (self.state.table(self.state.active_table).len, first_visible) {
+            self.state.active_table = table;
+        }
+    }
+
+    /// Turn the `--min-severity` filter on or off, showing at least minor updates if no severity was given.
+    fn toggle_severity_filter(&mut self) {
+        self.preserve_selection(|app| {
+            app.state.min_severity = match app.state.min_severity {
+                Some(_) => None,
+                None => Some(app.severity_filter),
+            };
+            app.refresh_lengths();
+        });
+        if let Some(min_severity) = self.state.min_severity {
+            self.state
+                .show_toast(format!("Showing {} updates and above", min_severity), false);
+        }
+    }
+
+    /// Something of the dependency selected in the given table.
+    fn selected<T>(&self, table: DependencyTable, f: impl FnOnce(&Dependency) -> T) -> Option<T> {
+        let selected_index = self.state.table(table).table_state.selected()?;
+
+        self.dependencies(table).get(selected_index).map(f)
+    }
+
+    /// Name of the package selected in the given table.
+    fn selected_name(&self, table: DependencyTable) -> Option<String> {
+        self.selected(table, |dependency| dependency.name.to_owned())
+    }
+
+    /// Key of the dependency selected in the given table, see `Dependency::key`.
+    fn selected_key(&self, table: DependencyTable) -> Option<String> {
+        self.selected(table, |dependency| dependency.key().to_owned())
+    }
+
+    /// Select the row with the given key, wherever the current sort order puts it.
+    fn select_key(&mut self, table: DependencyTable, key: &str) {
+        let index = self
+            .dependencies(table)
+            .iter()
+            .position(|dependency| dependency.key() == key);
+
+        if let Some(index) = index {
+            self.state.table_mut(table).table_state.select(Some(index));
+        }
+    }
+
+    /// Apply a change that may reorder rows while keeping the same packages selected.
+    fn preserve_selection(&mut self, change: impl FnOnce(&mut Self)) {
+        let selected = DependencyTable::ALL.map(|table| (table, self.selected_key(table)));
+
+        change(self);
+
+        for (table, key) in selected {
+            if let Some(key) = key {
+                self.select_key(table, &key);
             }
         }
     }
 
-    fn update_package_json(&mut self) {
-        if self.state.update_index.is_empty() && self.state.dev_update_index.is_empty() {
+    /// Switch between comparing the declared ranges and the versions installed in `node_modules` against latest.
+    fn toggle_installed_comparison(&mut self) {
+        self.preserve_selection(|app| {
+            app.selection.compares_installed = !app.selection.compares_installed;
+            app.refresh_lengths();
+        });
+        let message = match self.selection.compares_installed {
+            true => "Comparing installed versions against latest",
+            false => "Comparing declared ranges against latest",
+        };
+        self.state.show_toast(message.to_owned(), false);
+    }
+
+    fn cycle_sort_mode(&mut self) {
+        self.preserve_selection(|app| app.state.sort_mode = app.state.sort_mode.next());
+    }
+
+    fn show_dependents(&mut self) {
+        let name = match self.selected_name(self.state.active_table) {
+            Some(name) => name,
+            None => return,
+        };
+        let lookup = self.lockfile.as_ref().map(|lockfile| DependentsLookup {
+            dependents: to_owned_names(lockfile.dependents(&name)),
+            direct_dependents: to_owned_names(lockfile.direct_dependents(&name)),
+        });
+
+        self.state.dependents = Some(Dependents { name, lookup });
+    }
+
+    /// Open the release notes popup of the selected package, returning the versions to fetch releases between.
+    fn show_release_notes(&mut self) -> Option<(String, Version, Version)> {
+        let (name, declared) = self.selected(self.state.active_table, |dependency| {
+            (dependency.name.to_owned(), dependency.declared.to_owned())
+        })?;
+        if self.github_token.is_none() {
+            self.state
+                .show_toast("Pass --github-token to see release notes".to_owned(), true);
+            return None;
+        }
+        let latest = match self.fetched_packages.get(&name) {
+            Some(fetched) => Version::parse(&fetched.latest).ok()?,
+            None => {
+                self.state
+                    .show_toast(format!("{} is still being fetched", name), true);
+                return None;
+            }
+        };
+        let current = version::current_version(&declared)?;
+
+        self.state.release_notes = Some(ReleaseNotes {
+            name: name.clone(),
+            releases: None,
+        });
+        Some((name, current, latest))
+    }
+
+    fn toggle_update(&mut self) {
+        let selected = self.selected(self.state.active_table, |dependency| {
+            (dependency.key().to_owned(), dependency.is_workspace_link())
+        });
+        let (selected_key, is_workspace_link) = match selected {
+            Some(selected) => selected,
+            None => return,
+        };
+        if !self.selection.updates(self.state.active_table) {
+            self.state.show_toast(
+                "Peer dependencies are only shown, pass --update-peers to update them".to_owned(),
+                true,
+            );
+            return;
+        }
+        if is_workspace_link {
             return;
         }
 
-        let project = self.project.clone();
-        let dependencies = project.dependencies().into_iter().flatten();
-        let dev_dependencies = project.dev_dependencies().into_iter().flatten();
-
-        for (i, (name, version)) in dependencies.enumerate() {
-            if self.state.update_index.contains(&i) {
-                let latest_version = self
-                    .fetched_packages
-                    .get(name)
-                    .expect("Unable to get the latest version to update package");
-
-                let range_prefix = match version.as_str().unwrap().chars().next() {
-                    Some('~') | Some('^') => version.as_str().unwrap().chars().next(),
-                    _ => None,
+        let update_index = &mut self.state.table_mut(self.state.active_table).update_index;
+
+        if !update_index.remove(&selected_key) {
+            update_index.insert(selected_key);
+        }
+    }
+
+    /// Mark every shown update, or unmark them all if they are already marked.
+    ///
+    /// With `--update-up-to`, packages whose target is too severe are marked with the newest allowed target instead.
+    fn toggle_all_updates(&mut self) {
+        let mut updates = Vec::new();
+        // Name, declared range and allowed target of packages whose target is lowered when they are marked
+        let mut lowered_targets = Vec::new();
+        for table in DependencyTable::ALL {
+            if !self.selection.updates(table) || self.no_bulk_dev && table == DependencyTable::Dev {
+                continue;
+            }
+            for dependency in self.dependencies(table) {
+                if dependency.is_workspace_link() {
+                    continue;
+                }
+                let latest = dependency.latest;
+                let limited = match dependency.with_update_up_to(self.update_up_to) {
+                    Some(limited) => limited,
+                    None => continue,
                 };
+                if let Some(target) = limited.latest.filter(|&target| Some(target) != latest) {
+                    let (name, declared) = (limited.name.to_owned(), limited.declared.to_owned());
+                    lowered_targets.push((name, declared, target.to_owned()));
+                }
+                updates.push((table, limited.key().to_owned()));
+            }
+        }
+        let is_marked = updates
+            .iter()
+            .all(|(table, key)| self.state.update_index(*table).contains(key));
 
-                self.project
-                    .update_dependency_version(name, latest_version, range_prefix);
+        for (table, key) in &updates {
+            let update_index = &mut self.state.table_mut(*table).update_index;
+            if is_marked {
+                update_index.remove(key);
+            } else {
+                update_index.insert(key.clone());
             }
         }
+        if !is_marked {
+            // The severity of the new targets can move packages when sorting by severity
+            self.preserve_selection(|app| {
+                for (name, declared, target) in lowered_targets {
+                    if let Some(fetched) = app.fetched_packages.get_mut(&name) {
+                        fetched.for_range_mut(&declared).target = target;
+                    }
+                }
+            });
+        }
 
-        for (i, (name, version)) in dev_dependencies.enumerate() {
-            if self.state.dev_update_index.contains(&i) {
-                let latest_version = self
-                    .fetched_packages
-                    .get(name)
-                    .expect("Unable to get the latest version to update package");
+        let mut message = match is_marked {
+            true => format!("Unmarked {} updates", updates.len()),
+            false => format!("Marked {} updates", updates.len()),
+        };
+        if let Some(update_up_to) = self.update_up_to {
+            message += &format!(" up to {}", update_up_to);
+        }
+        if self.no_bulk_dev {
+            message += ", development dependencies are marked one at a time";
+        }
+        self.state.show_toast(message, false);
+    }
 
-                let range_prefix = match version.as_str().unwrap().chars().next() {
-                    Some('~') | Some('^') => version.as_str().unwrap().chars().next(),
-                    _ => None,
-                };
+    /// Choose a larger or smaller update target for the selected package, if it is marked for update.
+    fn step_target(&mut self, step: isize) {
+        let table = self.state.active_table;
+        let selected = self.selected(table, |dependency| {
+            let key = dependency.key().to_owned();
+            (
+                key,
+                dependency.name.to_owned(),
+                dependency.declared.to_owned(),
+            )
+        });
+        let (name, declared) = match selected {
+            Some((key, name, declared)) if self.state.update_index(table).contains(&key) => {
+                (name, declared)
+            }
+            _ => return,
+        };
+
+        // The severity of the new target can move the package when sorting by severity
+        self.preserve_selection(|app| {
+            if let Some(fetched) = app.fetched_packages.get_mut(&name) {
+                fetched.for_range_mut(&declared).step_target(step);
+            }
+        });
+    }
+
+    /// Changes of the marked updates, plus the names of marked unions of ranges that `--union-ranges` skips.
+    fn marked_changes(&self) -> (Vec<VersionChange>, Vec<String>) {
+        let marked: Vec<_> = DependencyTable::ALL
+            .into_iter()
+            .flat_map(|table| {
+                Dependency::list(
+                    &self.project,
+                    &self.selection,
+                    &self.fetched_packages,
+                    table,
+                    SortMode::Manifest,
+                    self.state.update_index(table),
+                )
+            })
+            .filter(|dependency| dependency.marked)
+            .collect();
+
+        let skipped = update::skipped_unions(&marked, self.union_ranges)
+            .map(|dependency| dependency.name.to_owned())
+            .collect();
+
+        (update::plan(&marked, self.union_ranges), skipped)
+    }
+
+    /// Show the changes of the marked updates for confirmation, returning whether `package.json` is already updated.
+    ///
+    /// There is nothing to confirm if no marked update changes a declared version, so that case is handled right away.
+    fn confirm_update(&mut self, after_update: AfterUpdate) -> bool {
+        let (changes, skipped) = self.marked_changes();
+        if !skipped.is_empty() {
+            let message = format!(
+                "Left {} as they are, ranges joined by || are only updated with --union-ranges",
+                skipped.join(", ")
+            );
+            self.state.show_toast(message, true);
+            if changes.is_empty() {
+                return false;
+            }
+        }
+        if changes.is_empty() {
+            return self.update_package_json(&changes);
+        }
+
+        if let Some(warning) = &self.uncommitted_changes_warning {
+            self.state.show_toast(warning.clone(), true);
+        }
+        self.state.confirmation = Some(Confirmation {
+            changes,
+            after_update,
+        });
+        false
+    }
 
-                self.project
-                    .update_dependency_version(name, latest_version, range_prefix);
+    /// Write the confirmed `changes` to `package.json`, returning whether that succeeded.
+    ///
+    /// On failure the project and the marks are kept as they were, so that the update can be retried.
+    fn update_package_json(&mut self, changes: &[VersionChange]) -> bool {
+        if self
+            .state
+            .tables
+            .iter()
+            .all(|view| view.update_index.is_empty())
+        {
+            return true;
+        }
+
+        let project = self.project.clone();
+        update::apply(&mut self.project, changes);
+        let file_name = self.project.file_name();
+        if !changes.is_empty() {
+            if let Err(e) = self.project.write_to_file() {
+                self.state
+                    .show_toast(format!("Unable to write {}: {}", file_name, e), true);
+                self.project = project;
+                return false;
             }
         }
 
-        self.project
-            .write_to_file()
-            .expect("Unable to write updates to package.json file");
+        let mut message = match changes.len() {
+            0 => format!("{} is already up to date", file_name),
+            1 => format!("Updated {} — 1 package", file_name),
+            n => format!("Updated {} — {} packages", file_name, n),
+        };
+        let flattened: Vec<&str> = changes
+            .iter()
+            .filter(|change| change.is_flattened)
+            .map(|change| change.name.as_str())
+            .collect();
+        if !flattened.is_empty() {
+            message += &format!(", replaced the ranges of {}", flattened.join(", "));
+        }
+        let log_result = match &self.log_file {
+            Some(log_file) if !changes.is_empty() => update::append_to_log(log_file, changes),
+            _ => Ok(()),
+        };
+        match log_result {
+            Ok(()) => self.state.show_toast(message, false),
+            Err(e) => self
+                .state
+                .show_toast(format!("{}, but unable to write log: {}", message, e), true),
+        }
+
+        // `self.project` now holds the written versions, so the marks no longer apply
+        for view in &mut self.state.tables {
+            view.update_index.clear();
+        }
+
+        true
+    }
+
+    /// Describe the marked updates, or every available update if none are marked, for a pull request.
+    fn compose_pull_request_body(&mut self) {
+        let updates: Vec<_> = self
+            .selection
+            .tables
+            .iter()
+            .flat_map(|&table| self.dependencies(table))
+            .filter(|dependency| dependency.severity.is_some())
+            .collect();
+        let (marked, unmarked): (Vec<_>, Vec<_>) = updates.into_iter().partition(|d| d.marked);
+        let entries = if marked.is_empty() { unmarked } else { marked };
+
+        self.pull_request_body = Some(text::pull_request_body(&entries));
+    }
+
+    /// Copy every update as the JSON of `--format json` to the clipboard.
+    fn copy_updates_as_json(&mut self) {
+        let updates: Vec<_> = self
+            .selection
+            .tables
+            .iter()
+            .flat_map(|&table| self.dependencies(table))
+            .filter(|dependency| dependency.severity.is_some())
+            .collect();
+        let (count, json) = (updates.len(), text::json(&updates));
+
+        match copy_to_clipboard(&json) {
+            Ok(()) => self
+                .state
+                .show_toast(format!("Copied {} updates as JSON", count), false),
+            Err(e) => self
+                .state
+                .show_toast(format!("Unable to copy to the clipboard: {}", e), true),
+        }
     }
 
     async fn event_loop(&mut self) {
         let tick_rate = Duration::from_millis(20);
 
-        // Process inputs in a separate task
+        // Process inputs on a blocking thread, since polling them would hold up the other tasks of the runtime
         let (tx, mut rx) = mpsc::channel(64);
-        tokio::spawn(async move {
+        tokio::task::spawn_blocking(move || {
             let mut last_tick = Instant::now();
 
             loop {
@@ -244,15 +955,23 @@ impl Application {
                     .unwrap_or_else(|| Duration::from_secs(0));
 
                 if event::poll(timeout).expect("Unable to poll events") {
-                    if let Event::Key(key) = event::read().expect("Unable to read events") {
-                        tx.send(ApplicationEvent::Input(key))
-                            .await
-                            .expect("Unable to send application events");
+                    let event = match event::read().expect("Unable to read events") {
+                        Event::Key(key) => Some(ApplicationEvent::Input(key)),
+                        Event::Resize(_, _) => Some(ApplicationEvent::Resize),
+                        _ => None,
+                    };
+                    // The application is gone once nothing receives the events
+                    if let Some(event) = event {
+                        if tx.blocking_send(event).is_err() {
+                            return;
+                        }
                     }
                 }
 
-                if last_tick.elapsed() >= tick_rate && tx.send(ApplicationEvent::Tick).await.is_ok()
-                {
+                if last_tick.elapsed() >= tick_rate {
+                    if tx.blocking_send(ApplicationEvent::Tick).is_err() {
+                        return;
+                    }
                     last_tick = Instant::now();
                 }
             }
@@ -261,58 +980,268 @@ impl Application {
         let backend = CrosstermBackend::new(stdout());
         let mut terminal = Terminal::new(backend).expect("Unable to create a terminal session");
 
-        let client = Client::new();
+        let client = self.client.clone();
+        let github_client = self.github_client.clone();
 
         let project = self.project.clone();
-        let dependencies = project.dependencies().into_iter().map(|d| d.keys());
-        let dev_dependencies = project.dev_dependencies().into_iter().map(|d| d.keys());
-        let all_dependencies = dependencies.chain(dev_dependencies).flatten();
-
-        let mut package_updates = stream::iter(all_dependencies)
-            .map(|package_name| {
-                let client = &client;
-                registry::fetch_metadata(client, package_name)
-            })
-            .buffer_unordered(10);
+        let selection = self.selection.clone();
+        let registries = self.registries.clone();
+        let cached_packages = match &self.disk_cache {
+            Some(disk_cache) => disk_cache.load(),
+            None => HashMap::new(),
+        };
+        for name in selection.names(&project) {
+            if let Some(package) = cached_packages.get(name) {
+                let ranges = selection.declared_ranges(&project, name);
+                let fetched = FetchedPackage::with_ranges(package, &ranges, &self.update_policy);
+                self.fetched_packages.insert(name.clone(), fetched);
+            }
+        }
+        self.refresh_lengths();
+        let missing_dependencies: Vec<&String> = selection
+            .names(&project)
+            .filter(|name| !self.fetched_packages.contains_key(*name))
+            .collect();
+
+        let mut package_updates = registry::fetch_all(
+            &client,
+            &registries,
+            missing_dependencies,
+            self.fetch_limits,
+        );
+        self.state.fetch_started = Instant::now();
+
+        // Single packages fetched again with `R`, bypassing the disk cache
+        let mut refetches = FuturesUnordered::new();
+        let refetch = |name: String| {
+            let (client, registries) = (&client, &registries);
+            async move {
+                let package = registry::fetch_with_retries(client, registries, &name).await;
+                (name, package)
+            }
+        };
 
+        // Release notes fetched with `l`, only one popup is shown so stale results are dropped when they arrive
+        let github_token = self.github_token.clone().unwrap_or_default();
+        let mut release_notes = FuturesUnordered::new();
+        let fetch_release_notes = |(name, current, latest): (String, Version, Version)| {
+            let (client, github_client) = (&client, &github_client);
+            let (registries, token) = (&registries, github_token.as_str());
+            async move {
+                let releases = github::release_notes(
+                    client,
+                    github_client,
+                    registries,
+                    token,
+                    &name,
+                    &current,
+                    &latest,
+                )
+                .await;
+                (name, releases)
+            }
+        };
+
+        // Only draw a frame after something visible changed, ticks alone would redraw 50 times per second
+        let mut is_dirty = true;
         loop {
-            terminal
-                .draw(|f| draw_ui(f, &self.project, &self.fetched_packages, &mut self.state))
-                .expect("Unable to draw a terminal frame");
+            if is_dirty {
+                terminal
+                    .draw(|f| {
+                        draw_ui(
+                            f,
+                            &self.project,
+                            &self.selection,
+                            &self.fetched_packages,
+                            &mut self.state,
+                        )
+                    })
+                    .expect("Unable to draw a terminal frame");
+                is_dirty = false;
+            }
 
             tokio::select! {
                 biased;
 
                 Some(event) = rx.recv() => {
+                    if !matches!(event, ApplicationEvent::Tick) {
+                        is_dirty = true;
+                    }
                     match event {
+                        ApplicationEvent::Input(key) if self.state.confirmation.is_some() => match key.code {
+                            KeyCode::Enter | KeyCode::Char('y') => {
+                                let confirmation = self.state.confirmation.take().expect("confirmation is shown");
+                                // Stay open to show why the update failed
+                                if self.update_package_json(&confirmation.changes) {
+                                    match confirmation.after_update {
+                                        AfterUpdate::Stay => {}
+                                        AfterUpdate::Close => break,
+                                        AfterUpdate::FetchAgain => {
+                                            let names: HashSet<String> =
+                                                confirmation.changes.into_iter().map(|change| change.name).collect();
+                                            refetches.extend(names.into_iter().map(refetch));
+                                        }
+                                    }
+                                }
+                            }
+                            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => self.state.confirmation = None,
+                            _ => {}
+                        },
+                        ApplicationEvent::Input(key) if self.state.dependents.is_some() => match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('w') => self.state.dependents = None,
+                            _ => {}
+                        },
+                        ApplicationEvent::Input(key) if self.state.shows_fetch_errors => match key.code {
+                            KeyCode::Char('r') => refetches.extend(self.failed_names().into_iter().map(refetch)),
+                            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('e') => self.state.shows_fetch_errors = false,
+                            _ => {}
+                        },
+                        ApplicationEvent::Input(key) if self.state.release_notes.is_some() => match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('l') => self.state.release_notes = None,
+                            _ => {}
+                        },
                         ApplicationEvent::Input(key) => match key.code {
                             KeyCode::Down => self.next(),
                             KeyCode::Up => self.previous(),
+                            KeyCode::Char('n') => self.jump_to_update(false),
+                            KeyCode::Char('N') => self.jump_to_update(true),
                             KeyCode::Tab | KeyCode::BackTab => self.switch_table(),
                             KeyCode::Enter | KeyCode::Char(' ') => self.toggle_update(),
-                            KeyCode::Char('u') => self.update_package_json(),
+                            KeyCode::Char('a') => self.toggle_all_updates(),
+                            KeyCode::Char('+') | KeyCode::Char('=') => self.step_target(1),
+                            KeyCode::Char('-') => self.step_target(-1),
+                            KeyCode::Char('s') => self.cycle_sort_mode(),
+                            KeyCode::Char('c') => self.state.visible_columns = self.state.visible_columns.next(),
+                            KeyCode::Char('f') => self.toggle_severity_filter(),
+                            KeyCode::Char('i') => self.toggle_installed_comparison(),
+                            KeyCode::Char('w') => self.show_dependents(),
+                            KeyCode::Char('l') => if let Some(versions) = self.show_release_notes() {
+                                release_notes.push(fetch_release_notes(versions));
+                            },
+                            KeyCode::Char('e') => match self.state.fetch_errors.is_empty() {
+                                true => self.state.show_toast("No package failed to fetch".to_owned(), false),
+                                false => self.state.shows_fetch_errors = true,
+                            },
+                            KeyCode::Char('R') => if let Some(name) = self.refetch_name() {
+                                refetches.push(refetch(name));
+                            },
+                            KeyCode::Char('u') => {
+                                self.confirm_update(AfterUpdate::Stay);
+                            }
+                            KeyCode::Char('r') => {
+                                self.confirm_update(AfterUpdate::FetchAgain);
+                            }
+                            KeyCode::Char('y') => self.copy_updates_as_json(),
+                            KeyCode::Char('p') => {
+                                self.compose_pull_request_body();
+                                break;
+                            }
+                            KeyCode::Char('U') if self.confirm_update(AfterUpdate::Close) => break,
                             KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => break,
                             _ => {}
                         },
-                        ApplicationEvent::Tick => {}
+                        ApplicationEvent::Resize => {}
+                        // The throughput shown while fetching changes as completions fall out of its window
+                        ApplicationEvent::Tick => if self.state.prune_fetch_completions() | self.state.prune_toast() {
+                            is_dirty = true;
+                        },
                     }
                 }
-                Some(package) = package_updates.next() => {
+                Some((name, package)) = package_updates.next() => {
+                    is_dirty = true;
+                    self.state.record_fetch_completion();
+
+                    match package {
+                        Ok(package) => self.receive_package(package),
+                        Err(e) => self.state.fetch_errors.push((name.to_owned(), e)),
+                    }
+
+                }
+                Some((name, package)) = refetches.next() => {
+                    is_dirty = true;
+
                     match package {
                         Ok(package) => {
-                            self.fetched_packages.insert(package.name, package.dist_tags.latest);
-                        },
-                        Err(_e) => {
-                            todo!();
+                            self.state.fetch_errors.retain(|(failed, _)| *failed != name);
+                            let message = format!("{}: latest is {}", name, package.dist_tags.latest);
+                            self.receive_package(package);
+                            self.state.show_toast(message, false);
+                        }
+                        // The previous result is kept, it's likely still more useful than nothing
+                        Err(e) => {
+                            self.state.show_toast(format!("Could not fetch {}: {}", name, e), true);
+                            // Packages that keep failing show their latest error
+                            if let Some((_, error)) = self.state.fetch_errors.iter_mut().find(|(failed, _)| *failed == name) {
+                                *error = e;
+                            }
                         }
                     }
-
+                }
+                Some((name, releases)) = release_notes.next() => {
+                    if let Some(notes) = self.state.release_notes.as_mut().filter(|notes| notes.name == name) {
+                        is_dirty = true;
+                        notes.releases = Some(releases.map_err(|e| e.to_string()));
+                    }
                 }
                 else => { break }
             };
         }
     }
 
+    /// Name of the selected package if it can be fetched again, i.e. its first fetch has completed.
+    fn refetch_name(&mut self) -> Option<String> {
+        let name = self.selected_name(self.state.active_table)?;
+        let is_fetched = self.fetched_packages.contains_key(&name)
+            || self
+                .state
+                .fetch_errors
+                .iter()
+                .any(|(failed, _)| *failed == name);
+
+        if is_fetched {
+            self.state
+                .show_toast(format!("Fetching {} again…", name), false);
+            Some(name)
+        } else {
+            self.state
+                .show_toast(format!("{} is still being fetched", name), true);
+            None
+        }
+    }
+
+    /// Names of the packages that failed to fetch, closing their popup to fetch them all again.
+    fn failed_names(&mut self) -> Vec<String> {
+        self.state.shows_fetch_errors = false;
+        let names: Vec<String> = self
+            .state
+            .fetch_errors
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect();
+        self.state
+            .show_toast(format!("Fetching {} packages again…", names.len()), false);
+
+        names
+    }
+
+    /// Keep the freshly fetched metadata of a package, both in the tables and in the disk cache.
+    fn receive_package(&mut self, package: Metadata) {
+        // Persist right away, so that the package isn't fetched again after Ctrl+C
+        if let Some(disk_cache) = &self.disk_cache {
+            if disk_cache.insert(&package).is_err() {
+                self.disk_cache = None;
+            }
+        }
+        let ranges = self.selection.declared_ranges(&self.project, &package.name);
+        let fetched = FetchedPackage::with_ranges(&package, &ranges, &self.update_policy);
+
+        // A new update target can move the package when sorting by severity, or show it when filtering
+        self.preserve_selection(|app| {
+            app.fetched_packages.insert(package.name, fetched);
+            app.refresh_lengths();
+        });
+    }
+
     fn claim_terminal(&mut self) -> Result<(), Error> {
         terminal::enable_raw_mode()?;
         let mut stdout = stdout();
@@ -350,6 +1279,19 @@ impl Application {
 
         self.restore_terminal()?;
 
+        if let Some(body) = &self.pull_request_body {
+            print!("{}", body);
+        }
+
         Ok(())
     }
 }
+
+/// Copy `text` to the clipboard with the OSC 52 escape sequence, which the terminal handles even over SSH.
+///
+/// Terminals without support ignore the sequence, there is no way to tell.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64::encode(text))?;
+    stdout.flush()
+}
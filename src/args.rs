@@ -1,11 +1,668 @@
-use std::path::PathBuf;
+use std::{env, fs, io, path::PathBuf, time::Duration};
 
-use clap::Parser;
+use anyhow::{anyhow, Error};
+use clap::{ArgEnum, ArgGroup, Parser};
+use node_semver::Range;
+use reqwest::{Certificate, Client};
+use tokio::time::Instant;
+
+use crate::{
+    application::{DependencyTable, SortMode},
+    project::Project,
+    registry::{self, FetchLimits, Pool, Registries, Tls},
+    version::VersionSection,
+};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
+// Options of writing updates apply to `--update-all` and `--set` alike
+#[clap(group(ArgGroup::new("updates").multiple(true).args(&["update-all", "set"])))]
 pub struct Args {
-    /// Path to a directory that contains a package.json file
-    #[clap(parse(from_os_str))]
+    /// Only check these packages, which must be dependencies in package.json
+    pub packages: Vec<String>,
+
+    /// Path to a directory that contains a package.json file, or a deno.json(c) file for Deno projects, or to the file
+    /// itself
+    #[clap(short, long, parse(from_os_str), env = "PACKRAT_DIRECTORY")]
     pub directory: Option<PathBuf>,
+
+    /// Name of the manifest file in the directory, e.g. to check a renamed backup
+    #[clap(long, value_name = "FILENAME", default_value = "package.json")]
+    pub manifest: String,
+
+    /// Report on every manifest matched by this glob, e.g. '**/package.json', implies `--report`
+    #[clap(long, value_name = "GLOB", conflicts_with_all = &["packages", "directory", "update-all"])]
+    pub projects: Option<String>,
+
+    /// Report on the project and each of its workspace packages under its own heading, implies `--report`
+    #[clap(long, conflicts_with_all = &["packages", "projects", "update-all"])]
+    pub workspaces: bool,
+
+    /// Base URL of the npm registry to fetch package metadata from [default: `publishConfig.registry` of package.json, then the npm registry]
+    #[clap(long, env = "PACKRAT_REGISTRY")]
+    pub registry: Option<String>,
+
+    /// Registry of the packages of a scope, e.g. `@myorg=https://npm.example.com/`. Repeat it for several scopes
+    #[clap(
+        long,
+        value_name = "SCOPE=URL",
+        multiple_occurrences = true,
+        parse(try_from_str = parse_scope_registry)
+    )]
+    pub scope_registry: Vec<(String, String)>,
+
+    /// Update to the newest version allowed by the declared range instead of the latest version
+    #[clap(long)]
+    pub in_range: bool,
+
+    /// Update to the version with this dist-tag instead of `latest`, if a package has it. Per-package tags are read from packrat.toml
+    #[clap(long, value_name = "TAG", conflicts_with = "in-range")]
+    pub tag: Option<String>,
+
+    /// Update to the version N stable releases older than the latest one, to stay slightly behind it
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        conflicts_with = "in-range"
+    )]
+    pub lag: usize,
+
+    /// Compare the versions installed in `node_modules` against the latest ones instead of the declared ranges, like
+    /// the Current column of `npm outdated`. Packages that are not installed fall back to their declared range
+    #[clap(long)]
+    pub installed: bool,
+
+    /// Allow updating to prerelease versions, which are otherwise only considered for prerelease dependencies
+    #[clap(long)]
+    pub include_prerelease: bool,
+
+    /// Skip versions that require a newer Node than the project supports (`engines.node` or `.nvmrc`)
+    #[clap(long, requires = "in-range")]
+    pub respect_engines: bool,
+
+    /// `User-Agent` header of registry requests, e.g. to match a proxy allowlist [default: packrat/<version>]
+    #[clap(long)]
+    pub user_agent: Option<String>,
+
+    /// Root certificate to trust in addition to the system ones, in PEM or DER format, e.g. of a registry with a
+    /// self-signed certificate
+    #[clap(long, value_name = "PATH", parse(from_os_str))]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Don't verify the TLS certificates of registries. Only meant for testing internal registries, since anyone on the
+    /// network can then impersonate them. Release notes are still fetched from GitHub with verified certificates
+    #[clap(long)]
+    pub insecure: bool,
+
+    /// Fetch every package from the registry instead of reusing metadata fetched within the last 10 minutes
+    #[clap(long)]
+    pub no_cache: bool,
+
+    /// GitHub token to show the release notes of updates with `l`, which the GitHub API rate limits without one
+    #[clap(long, env = "PACKRAT_GITHUB_TOKEN", hide_env_values = true)]
+    pub github_token: Option<String>,
+
+    /// Open a new connection for every request instead of reusing idle ones, e.g. for a proxy that mishandles keep-alive
+    #[clap(long)]
+    pub no_keepalive: bool,
+
+    /// Close connections to registries after they have been idle this long, e.g. `30s` [default: 90s]
+    #[clap(
+        long,
+        value_name = "DURATION",
+        conflicts_with = "no-keepalive",
+        parse(try_from_str = humantime::parse_duration)
+    )]
+    pub pool_idle_timeout: Option<Duration>,
+
+    /// Print on stderr when connections to registries are opened and reused, to check that they are pooled. Implies
+    /// `--report`, since the interactive UI would draw over it
+    #[clap(long)]
+    pub log_connections: bool,
+
+    /// Run on N worker threads, for environments where the default of one per CPU misbehaves. `1` runs on one worker
+    /// thread, while input polling and DNS lookups still use blocking threads
+    #[clap(long, value_name = "N", parse(try_from_str = parse_threads))]
+    pub threads: Option<usize>,
+
+    /// Stop waiting for the registry after this long in total, e.g. `30s`, and continue with what was fetched
+    #[clap(long, value_name = "DURATION", parse(try_from_str = humantime::parse_duration))]
+    pub timeout_total: Option<Duration>,
+
+    /// Fetch packages N at a time with a pause in between, for registries that limit bursts of requests
+    #[clap(long, value_name = "N", parse(try_from_str = parse_batch_size))]
+    pub batch_size: Option<usize>,
+
+    /// Pause between the batches of `--batch-size`, e.g. `500ms`
+    #[clap(
+        long,
+        value_name = "DURATION",
+        default_value = "1s",
+        requires = "batch-size",
+        parse(try_from_str = humantime::parse_duration)
+    )]
+    pub batch_delay: Duration,
+
+    /// Only check and update `dependencies` and `optionalDependencies`, dev dependencies are neither fetched nor changed
+    #[clap(
+        long,
+        visible_alias = "no-dev",
+        alias = "production",
+        conflicts_with = "dev-only"
+    )]
+    pub prod_only: bool,
+
+    /// Only check and update `devDependencies`
+    #[clap(long)]
+    pub dev_only: bool,
+
+    /// Update `peerDependencies` too, which are only shown otherwise since raising a peer range drops support for
+    /// older versions. Their updates then also count for `--print-outdated-names` and the exit code of `--report`
+    #[clap(long, conflicts_with_all = &["prod-only", "dev-only"])]
+    pub update_peers: bool,
+
+    /// Check and update the versions pinned by `overrides` too. Overrides are deliberate pins, so they are left
+    /// alone by default, except for the top-level pins of declared dependencies that follow their declared versions
+    #[clap(long, conflicts_with_all = &["prod-only", "dev-only"])]
+    pub overrides: bool,
+
+    /// Append a timestamped line to this file for every update applied to package.json
+    #[clap(long, parse(from_os_str), value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Update every outdated dependency in package.json instead of starting the interactive UI
+    #[clap(long, conflicts_with_all = &["report", "format"])]
+    pub update_all: bool,
+
+    /// Write this exact version of a dependency without fetching it, e.g. `react=18.2.0`. Repeat it for several
+    /// packages. Only those are updated, unless `--update-all` is given too
+    #[clap(
+        long,
+        value_name = "NAME=VERSION",
+        multiple_occurrences = true,
+        parse(try_from_str = parse_version_assignment),
+        conflicts_with_all = &["report", "format", "projects", "workspaces"]
+    )]
+    pub set: Vec<(String, String)>,
+
+    /// Remove devDependencies that are also listed in dependencies, and keys listed twice, then exit
+    #[clap(long, conflicts_with_all = &["updates", "projects", "report", "format"])]
+    pub fix_duplicates: bool,
+
+    /// Apply `--update-all` or `--set` without asking for confirmation
+    #[clap(long, requires = "updates")]
+    pub yes: bool,
+
+    /// Order of the dependencies in the interactive UI when it starts, `s` changes it later
+    #[clap(long, arg_enum, value_name = "ORDER", default_value = "manifest")]
+    pub sort: SortMode,
+
+    /// Leave development dependencies out of `--update-all` and of marking every update with `a`, they can still be marked one at a time
+    #[clap(long)]
+    pub no_bulk_dev: bool,
+
+    /// Only include updates up to this severity in `--update-all` and in marking every update with `a`, e.g. `minor`
+    /// for minor and patch updates. More severe ones can still be marked one at a time
+    #[clap(long, arg_enum, value_name = "SEVERITY")]
+    pub update_up_to: Option<VersionSection>,
+
+    /// Draw borders and symbols with ASCII characters only, for terminals or fonts that show other ones garbled
+    #[clap(long)]
+    pub ascii: bool,
+
+    /// Don't warn before writing package.json when it has changes that are not committed to git
+    #[clap(long)]
+    pub no_git_check: bool,
+
+    /// Print the changes of `--update-all` or `--set` as a diff instead of writing package.json
+    #[clap(long, requires = "updates")]
+    pub dry_run: bool,
+
+    /// Only show dependencies with an update at least this severe in the interactive UI, `f` toggles the filter
+    #[clap(long, arg_enum, value_name = "SEVERITY")]
+    pub min_severity: Option<VersionSection>,
+
+    /// How to update ranges that allow several versions with `||`, e.g. `^16 || ^17`
+    #[clap(long, arg_enum, value_name = "POLICY", default_value = "skip")]
+    pub union_ranges: UnionRanges,
+
+    /// Print a report of outdated dependencies instead of starting the interactive UI
+    #[clap(long)]
+    pub report: bool,
+
+    /// Output format of the report, implies `--report`
+    #[clap(long, arg_enum)]
+    pub format: Option<ReportFormat>,
+
+    /// Exit with a failure if any package could not be fetched for the report, listing them on stderr. Optional peers
+    /// missing from the registry don't count
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Print only the names of packages with updates, one per line, e.g. to pipe into `xargs`
+    #[clap(long, conflicts_with_all = &["format", "updates"])]
+    pub print_outdated_names: bool,
+
+    /// List the packages with a new latest version since they were last fetched, however long ago that was
+    #[clap(
+        long,
+        conflicts_with_all = &["no-cache", "projects", "workspaces", "updates"]
+    )]
+    pub diff_cache: bool,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+    /// Columns of `npm outdated`, for scripts that parse its output
+    NpmOutdated,
+    /// Updates grouped into patch, minor and major
+    Grouped,
+    /// Comma-separated values with a header line, for spreadsheets
+    Csv,
+    /// Names of the packages with updates, one per line
+    Names,
+    /// An array with an object per update, e.g. for `jq`
+    Json,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnionRanges {
+    /// Leave them as they are and warn about them
+    Skip,
+    /// Replace the whole range with a caret range of the update target, e.g. `^18.2.0`
+    Replace,
+    /// Add a caret range of the update target, e.g. `^16 || ^17 || ^18.2.0`
+    Extend,
+}
+
+impl Args {
+    /// Path to the manifest file of the project, `package.json` unless `--manifest` is given.
+    ///
+    /// `--directory` may also point at the manifest file itself, e.g. `./foo/package.json`. A directory without a
+    /// `package.json` but with a `deno.json` or `deno.jsonc` is a Deno project, whose Deno file is read instead.
+    pub fn manifest_path(&self) -> io::Result<PathBuf> {
+        let mut path = match &self.directory {
+            Some(custom_directory) if custom_directory.is_file() => {
+                return Ok(custom_directory.clone())
+            }
+            Some(custom_directory) => custom_directory.clone(),
+            None => env::current_dir()?,
+        };
+        path.push(&self.manifest);
+        if self.manifest == "package.json" && !path.exists() {
+            let deno_json = ["deno.json", "deno.jsonc"]
+                .into_iter()
+                .map(|file_name| path.with_file_name(file_name))
+                .find(|deno_json| deno_json.is_file());
+            if let Some(deno_json) = deno_json {
+                return Ok(deno_json);
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Dependency tables selected by `--prod-only`, `--dev-only` and `--overrides`, all but overrides by default.
+    pub fn tables(&self) -> Vec<DependencyTable> {
+        let mut tables = Vec::new();
+        if !self.dev_only {
+            tables.push(DependencyTable::Runtime);
+        }
+        if !self.prod_only {
+            tables.push(DependencyTable::Dev);
+        }
+        if !self.prod_only && !self.dev_only {
+            tables.push(DependencyTable::Peer);
+        }
+        // Optional dependencies are installed in production too, unlike dev dependencies
+        if !self.dev_only {
+            tables.push(DependencyTable::Optional);
+        }
+        if self.overrides {
+            tables.push(DependencyTable::Overrides);
+        }
+
+        tables
+    }
+
+    /// Base URL of the registry to fetch package metadata from.
+    ///
+    /// In order of precedence: `--registry` or `PACKRAT_REGISTRY`, then `publishConfig.registry` of the project,
+    /// then the npm registry.
+    pub fn registry_url<'a>(&'a self, project: &'a Project) -> &'a str {
+        self.registry
+            .as_deref()
+            .or_else(|| project.publish_registry())
+            .unwrap_or(registry::REGISTRY_URL)
+    }
+
+    /// Registry of each package of the project, where a later `--scope-registry` of the same scope wins.
+    pub fn registries(&self, project: &Project) -> Registries {
+        Registries {
+            default: self.registry_url(project).to_owned(),
+            scopes: self.scope_registry.iter().cloned().collect(),
+        }
+    }
+
+    /// `User-Agent` header of registry requests.
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(registry::USER_AGENT)
+    }
+
+    /// How to verify the TLS certificates of registries, with the certificate of `--ca-cert` read.
+    pub fn tls(&self) -> Result<Tls, Error> {
+        let ca_cert = match &self.ca_cert {
+            Some(path) => {
+                let contents = fs::read(path)
+                    .map_err(|error| anyhow!("Could not read {}: {}", path.display(), error))?;
+                let certificate = Certificate::from_pem(&contents)
+                    .or_else(|_| Certificate::from_der(&contents))
+                    .map_err(|error| {
+                        anyhow!("Invalid certificate {}: {}", path.display(), error)
+                    })?;
+                Some(certificate)
+            }
+            None => None,
+        };
+
+        Ok(Tls {
+            ca_cert,
+            insecure: self.insecure,
+        })
+    }
+
+    /// HTTP client for registry requests, with the `User-Agent`, TLS and pooling settings of the arguments.
+    pub fn client(&self) -> Result<Client, Error> {
+        let pool = Pool {
+            no_keepalive: self.no_keepalive,
+            idle_timeout: self.pool_idle_timeout,
+        };
+
+        Ok(registry::client(self.user_agent(), &self.tls()?, pool)?)
+    }
+
+    /// Limits on fetching packages, with the deadline of `--timeout-total` counted from now.
+    pub fn fetch_limits(&self) -> FetchLimits {
+        FetchLimits {
+            deadline: self.timeout_total.map(|timeout| Instant::now() + timeout),
+            batches: self.batch_size.map(|size| (size, self.batch_delay)),
+        }
+    }
+
+    /// Whether updates are written without starting the interactive UI, see `--update-all` and `--set`.
+    pub fn is_batch_update(&self) -> bool {
+        self.update_all || !self.set.is_empty()
+    }
+
+    pub fn is_report(&self) -> bool {
+        self.report
+            || self.format.is_some()
+            || self.print_outdated_names
+            || self.projects.is_some()
+            || self.workspaces
+            || self.log_connections
+    }
+
+    /// Format of the report, the plain text one unless another is asked for.
+    pub fn report_format(&self) -> ReportFormat {
+        match self.format {
+            _ if self.print_outdated_names => ReportFormat::Names,
+            Some(format) => format,
+            None => ReportFormat::Text,
+        }
+    }
+}
+
+/// Parse a `--batch-size` value, which has to be at least 1.
+fn parse_batch_size(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) => Err("a batch has to fetch at least 1 package".to_owned()),
+        Ok(size) => Ok(size),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+fn parse_threads(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) => Err("packrat needs at least 1 thread".to_owned()),
+        Ok(threads) => Ok(threads),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+/// Parse a `--set` value, e.g. `react=18.2.0`. Scoped names contain no `=`, so the first one separates the version.
+fn parse_version_assignment(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((_, version)) if Range::parse(version).is_err() => Err(format!(
+            "{} is not a semver version or range, e.g. react=18.2.0",
+            version
+        )),
+        Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+            Ok((name.to_owned(), version.to_owned()))
+        }
+        _ => Err("expected a package and a version, e.g. react=18.2.0".to_owned()),
+    }
+}
+
+/// Parse a `--scope-registry` value, e.g. `@myorg=https://npm.example.com/`.
+fn parse_scope_registry(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((scope, url)) if scope.len() > 1 && scope.starts_with('@') && !url.is_empty() => {
+            Ok((scope.to_owned(), url.to_owned()))
+        }
+        _ => Err("expected a scope and a URL, e.g. @myorg=https://npm.example.com/".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    // Environment variables are shared by all tests of a process, so the cases that need them run alone in a child
+    // process of the test binary that is given its own environment
+    #[test]
+    fn prefers_arguments_over_environment_variables() {
+        if env::var_os("PACKRAT_TEST_CHILD").is_none() {
+            let output = Command::new(env::current_exe().unwrap())
+                .args([
+                    "--exact",
+                    "args::tests::prefers_arguments_over_environment_variables",
+                    "--test-threads",
+                    "1",
+                ])
+                .env("PACKRAT_TEST_CHILD", "1")
+                .env("PACKRAT_DIRECTORY", "/from/env")
+                .env("PACKRAT_REGISTRY", "https://env.example.com/")
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // A filter that matches no test would pass as well
+            assert!(
+                output.status.success() && stdout.contains("1 passed"),
+                "{}",
+                stdout
+            );
+            return;
+        }
+
+        let project = Project::parse(
+            r#"{ "publishConfig": { "registry": "https://publish.example.com/" } }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        assert_eq!(args.directory, Some(PathBuf::from("/from/env")));
+        assert_eq!(args.registry_url(&project), "https://env.example.com/");
+
+        let args = Args::try_parse_from([
+            "packrat",
+            "--directory",
+            "/from/args",
+            "--registry",
+            "https://args.example.com/",
+        ])
+        .unwrap();
+        assert_eq!(args.directory, Some(PathBuf::from("/from/args")));
+        assert_eq!(args.registry_url(&project), "https://args.example.com/");
+
+        // No other test runs in the child process
+        env::remove_var("PACKRAT_DIRECTORY");
+        env::remove_var("PACKRAT_REGISTRY");
+
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        assert_eq!(args.directory, None);
+        assert_eq!(args.registry_url(&project), "https://publish.example.com/");
+        let project = Project::parse("{}").unwrap();
+        assert_eq!(args.registry_url(&project), registry::REGISTRY_URL);
+    }
+
+    #[test]
+    fn maps_scopes_to_registries() {
+        let args = Args::try_parse_from([
+            "packrat",
+            "--registry",
+            "https://npm.example.com/",
+            "--scope-registry",
+            "@myorg=https://old.example.com/",
+            "--scope-registry",
+            "@other=https://other.example.com/",
+            "--scope-registry",
+            "@myorg=https://internal.example.com/",
+        ])
+        .unwrap();
+        let registries = args.registries(&Project::parse("{}").unwrap());
+
+        assert_eq!(registries.url("@myorg/ui"), "https://internal.example.com/");
+        assert_eq!(registries.url("@other/ui"), "https://other.example.com/");
+        assert_eq!(registries.url("@types/node"), "https://npm.example.com/");
+        assert_eq!(registries.url("react"), "https://npm.example.com/");
+
+        for value in [
+            "myorg=https://internal.example.com/",
+            "@myorg",
+            "@=https://x/",
+        ] {
+            assert!(Args::try_parse_from(["packrat", "--scope-registry", value]).is_err());
+        }
+    }
+
+    #[test]
+    fn accepts_manifest_file_as_directory() {
+        let directory = env::temp_dir().join(format!("packrat-args-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let manifest = directory.join("package.json");
+        std::fs::write(&manifest, "{}").unwrap();
+
+        for path in [&directory, &manifest] {
+            let args =
+                Args::try_parse_from(["packrat", "--directory", path.to_str().unwrap()]).unwrap();
+            assert_eq!(args.manifest_path().unwrap(), manifest);
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn detects_deno_projects() {
+        let directory = env::temp_dir().join(format!("packrat-deno-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let deno_jsonc = directory.join("deno.jsonc");
+        std::fs::write(&deno_jsonc, "{}").unwrap();
+        let args =
+            Args::try_parse_from(["packrat", "--directory", directory.to_str().unwrap()]).unwrap();
+        assert_eq!(args.manifest_path().unwrap(), deno_jsonc);
+        let deno_json = directory.join("deno.json");
+        std::fs::write(&deno_json, "{}").unwrap();
+        assert_eq!(args.manifest_path().unwrap(), deno_json);
+
+        // A package.json takes precedence, e.g. in projects that also run on Node
+        let package_json = directory.join("package.json");
+        std::fs::write(&package_json, "{}").unwrap();
+        assert_eq!(args.manifest_path().unwrap(), package_json);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn selects_dependency_tables() {
+        let tables = |args: &[&str]| {
+            Args::try_parse_from([&["packrat"], args].concat())
+                .unwrap()
+                .tables()
+        };
+
+        assert_eq!(
+            tables(&[]),
+            [
+                DependencyTable::Runtime,
+                DependencyTable::Dev,
+                DependencyTable::Peer,
+                DependencyTable::Optional
+            ]
+        );
+        assert_eq!(tables(&["--overrides"]), DependencyTable::ALL);
+        for prod_only in ["--prod-only", "--no-dev", "--production"] {
+            assert_eq!(
+                tables(&[prod_only]),
+                [DependencyTable::Runtime, DependencyTable::Optional]
+            );
+        }
+        assert_eq!(tables(&["--dev-only"]), [DependencyTable::Dev]);
+        assert!(Args::try_parse_from(["packrat", "--prod-only", "--dev-only"]).is_err());
+    }
+
+    #[test]
+    fn only_sets_semver_versions() {
+        let set =
+            |value: &str| Args::try_parse_from(["packrat", "--set", value]).map(|args| args.set);
+
+        assert_eq!(
+            set("@types/node=^20.1.0").unwrap(),
+            [("@types/node".to_owned(), "^20.1.0".to_owned())]
+        );
+        assert!(set("react=18.2.0").is_ok());
+        assert!(set("react=banana").is_err());
+        assert!(set("react=").is_err());
+        assert!(set("react").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_ca_certs() {
+        let tls_error = |path: &str| {
+            Args::try_parse_from(["packrat", "--ca-cert", path])
+                .unwrap()
+                .tls()
+                .err()
+                .unwrap()
+                .to_string()
+        };
+
+        assert!(tls_error("/nonexistent/ca.pem").starts_with("Could not read /nonexistent/ca.pem"));
+        assert!(tls_error("Cargo.toml").starts_with("Invalid certificate Cargo.toml"));
+        assert!(Args::try_parse_from(["packrat"]).unwrap().tls().is_ok());
+    }
+
+    #[test]
+    fn tunes_the_connection_pool() {
+        let args = Args::try_parse_from(["packrat", "--pool-idle-timeout", "30s"]).unwrap();
+        assert_eq!(args.pool_idle_timeout, Some(Duration::from_secs(30)));
+        assert!(
+            Args::try_parse_from(["packrat", "--no-keepalive", "--pool-idle-timeout", "30s"])
+                .is_err()
+        );
+
+        // Logged connections would be drawn over by the interactive UI
+        let args = Args::try_parse_from(["packrat", "--log-connections"]).unwrap();
+        assert!(args.is_report());
+    }
+
+    #[test]
+    fn bounds_the_threads() {
+        let args = Args::try_parse_from(["packrat", "--threads", "1"]).unwrap();
+        assert_eq!(args.threads, Some(1));
+        assert_eq!(Args::try_parse_from(["packrat"]).unwrap().threads, None);
+        assert!(Args::try_parse_from(["packrat", "--threads", "0"]).is_err());
+    }
 }
@@ -0,0 +1,239 @@
+//! Registry metadata persisted between runs, so that an interrupted run can resume without fetching everything again.
+//!
+//! Every package is appended to a JSON Lines file as soon as it is fetched, so the file survives Ctrl+C.
+//! The last entry of each package is kept after it expires, for `--diff-cache` to tell what was released since,
+//! until it is older than `MAX_SNAPSHOT_AGE`.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    args::Args,
+    registry::{Metadata, Registries},
+};
+
+/// How long fetched metadata is reused, long enough to resume a run but short enough to notice new releases.
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// How long the last entry of a package is kept for `--diff-cache`, so that packages that are no longer checked
+/// don't stay in the file forever.
+const MAX_SNAPSHOT_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A line of the cache file.
+#[derive(Deserialize)]
+struct Entry {
+    registry: String,
+    /// Seconds since the Unix epoch.
+    fetched_at: u64,
+    metadata: Metadata,
+}
+
+pub struct DiskCache {
+    path: PathBuf,
+    /// Entries are only used if they come from the registry that the package is fetched from now.
+    registries: Registries,
+}
+
+impl DiskCache {
+    /// Cache of the packages fetched from the registry, or `None` with `--no-cache` or without a home directory.
+    pub fn new(args: &Args, registries: Registries) -> Option<Self> {
+        if args.no_cache {
+            return None;
+        }
+        let directory = match env::var_os("XDG_CACHE_HOME") {
+            Some(directory) => PathBuf::from(directory),
+            None => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+        };
+
+        Some(DiskCache {
+            path: directory.join("packrat").join("metadata.jsonl"),
+            registries,
+        })
+    }
+
+    /// Metadata fetched from the registry within `MAX_AGE`, by package name.
+    ///
+    /// Expired entries of every registry are dropped from the file on the way, except the last one of each package
+    /// within `MAX_SNAPSHOT_AGE`.
+    pub fn load(&self) -> HashMap<String, Metadata> {
+        let contents = fs::read_to_string(&self.path).unwrap_or_default();
+        let now = seconds_since_epoch();
+        let entries: Vec<(&str, Entry)> = contents
+            .lines()
+            .filter_map(|line| Some((line, serde_json::from_str(line).ok()?)))
+            .collect();
+
+        let mut newest = HashMap::new();
+        for (_, entry) in &entries {
+            let key = (entry.registry.clone(), entry.metadata.name.clone());
+            let fetched_at = newest.entry(key).or_insert(entry.fetched_at);
+            *fetched_at = entry.fetched_at.max(*fetched_at);
+        }
+
+        let mut packages = HashMap::new();
+        let mut kept_lines = String::new();
+        for (line, entry) in entries {
+            let age = now.saturating_sub(entry.fetched_at);
+            let is_fresh = age <= MAX_AGE.as_secs();
+            let key = (entry.registry.clone(), entry.metadata.name.clone());
+            if !is_fresh && (newest[&key] != entry.fetched_at || age > MAX_SNAPSHOT_AGE.as_secs()) {
+                continue;
+            }
+
+            kept_lines += line;
+            kept_lines.push('\n');
+            if is_fresh && entry.registry == self.registries.url(&entry.metadata.name) {
+                packages.insert(entry.metadata.name.clone(), entry.metadata);
+            }
+        }
+
+        if kept_lines.len() != contents.len() {
+            // Expired lines are only skipped if this fails, so it's not worth reporting
+            let _ = self.replace(&kept_lines);
+        }
+
+        packages
+    }
+
+    /// Last metadata fetched from the registry by package name, however old, with when it was fetched.
+    pub fn snapshots(&self) -> HashMap<String, (SystemTime, Metadata)> {
+        let contents = fs::read_to_string(&self.path).unwrap_or_default();
+
+        let mut snapshots = HashMap::new();
+        for line in contents.lines() {
+            let entry: Entry = match serde_json::from_str(line) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            if entry.registry == self.registries.url(&entry.metadata.name) {
+                let fetched_at = UNIX_EPOCH + Duration::from_secs(entry.fetched_at);
+                snapshots.insert(entry.metadata.name.clone(), (fetched_at, entry.metadata));
+            }
+        }
+
+        snapshots
+    }
+
+    /// Replace the contents of the file at once, so that runs appending to it meanwhile never see it half written.
+    ///
+    /// A line appended between reading and replacing the file is lost, which only costs fetching that package again.
+    fn replace(&self, contents: &str) -> io::Result<()> {
+        let temporary = self
+            .path
+            .with_extension(format!("jsonl.{}.tmp", std::process::id()));
+        fs::write(&temporary, contents)?;
+        if let Err(e) = fs::rename(&temporary, &self.path) {
+            let _ = fs::remove_file(&temporary);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Append the metadata of a package that was just fetched.
+    pub fn insert(&self, metadata: &Metadata) -> io::Result<()> {
+        let entry = json!({
+            "registry": self.registries.url(&metadata.name),
+            "fetched_at": seconds_since_epoch(),
+            "metadata": metadata,
+        });
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // A single write per line, so that concurrent runs don't interleave their lines
+        file.write_all(format!("{}\n", entry).as_bytes())
+    }
+}
+
+fn seconds_since_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk_cache(name: &str, registry_url: &str) -> DiskCache {
+        DiskCache {
+            path: env::temp_dir()
+                .join(format!("packrat-{}", std::process::id()))
+                .join(format!("{}.jsonl", name)),
+            registries: Registries {
+                default: registry_url.to_owned(),
+                scopes: HashMap::new(),
+            },
+        }
+    }
+
+    fn metadata(name: &str) -> Metadata {
+        serde_json::from_value(json!({
+            "name": name,
+            "dist-tags": { "latest": "1.3.0" },
+            "versions": { "1.3.0": { "engines": { "node": ">=14" } } },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn loads_fresh_packages_of_the_same_registry() {
+        let cache = disk_cache("fresh", "https://registry.npmjs.org/");
+        let _ = fs::remove_file(&cache.path);
+        cache.insert(&metadata("left-pad")).unwrap();
+        cache.insert(&metadata("react")).unwrap();
+
+        let other_registry = disk_cache("fresh", "https://npm.example.com/");
+        other_registry.insert(&metadata("lodash")).unwrap();
+        let expired = |name, fetched_at| {
+            json!({
+                "registry": "https://registry.npmjs.org/",
+                "fetched_at": fetched_at,
+                "metadata": metadata(name),
+            })
+        };
+        let an_hour_ago = seconds_since_epoch() - 60 * 60;
+        let mut file = OpenOptions::new().append(true).open(&cache.path).unwrap();
+        writeln!(file, "{}", expired("react", an_hour_ago)).unwrap();
+        writeln!(file, "{}", expired("chalk", an_hour_ago - 60)).unwrap();
+        writeln!(file, "{}", expired("chalk", an_hour_ago)).unwrap();
+        writeln!(file, "{}", expired("moment", 0)).unwrap();
+        writeln!(file, "truncated {{").unwrap();
+
+        let packages = cache.load();
+        let mut names: Vec<_> = packages.keys().collect();
+        names.sort();
+        assert_eq!(names, ["left-pad", "react"]);
+        assert_eq!(
+            packages["react"].versions["1.3.0"].node_engine(),
+            Some(">=14")
+        );
+
+        // Expired lines are dropped unless they are the last of their package and not too old, other registries are
+        // kept
+        assert_eq!(fs::read_to_string(&cache.path).unwrap().lines().count(), 4);
+
+        let snapshots = cache.snapshots();
+        assert_eq!(
+            snapshots["chalk"].0,
+            UNIX_EPOCH + Duration::from_secs(an_hour_ago)
+        );
+        assert!(!snapshots.contains_key("moment"));
+        assert!(!snapshots.contains_key("lodash"));
+        fs::remove_dir_all(cache.path.parent().unwrap()).unwrap();
+    }
+}
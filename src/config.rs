@@ -0,0 +1,127 @@
+//! Per-project settings read from a `packrat.toml` file next to `package.json`.
+//!
+//! ```toml
+//! [tags]
+//! react = "next"
+//!
+//! [notes]
+//! typescript = "pinned until the build supports 5.x"
+//!
+//! [severity]
+//! "pre-1.0" = "Red"
+//! minor = { Indexed = 208 }
+//! ```
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use anyhow::{anyhow, Error};
+use serde::Deserialize;
+use tui::style::Color;
+
+use crate::version::VersionSection;
+
+/// Name of the config file that is looked up in the project directory.
+pub const CONFIG_FILE: &str = "packrat.toml";
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Dist-tag to update each listed package to, instead of `--tag` or `latest`.
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    /// Short note about each listed package, e.g. why it's pinned, shown next to its name.
+    #[serde(default)]
+    pub notes: HashMap<String, String>,
+    /// Color of the updates of each severity in the interactive UI.
+    #[serde(default)]
+    pub severity: SeverityColors,
+}
+
+/// Colors of `tui`, e.g. `"LightRed"`, `{ Indexed = 208 }` or `{ Rgb = [255, 128, 0] }`.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeverityColors {
+    pub major: Color,
+    #[serde(rename = "pre-1.0")]
+    pub pre_v1: Color,
+    pub minor: Color,
+    pub patch: Color,
+}
+
+impl SeverityColors {
+    /// Color of the updates of a severity.
+    pub fn color(&self, section: VersionSection) -> Color {
+        match section {
+            VersionSection::Major => self.major,
+            VersionSection::PreV1 => self.pre_v1,
+            VersionSection::Minor => self.minor,
+            VersionSection::Patch => self.patch,
+        }
+    }
+}
+
+impl Default for SeverityColors {
+    fn default() -> Self {
+        SeverityColors {
+            major: Color::Red,
+            pre_v1: Color::Magenta,
+            minor: Color::Yellow,
+            patch: Color::Green,
+        }
+    }
+}
+
+impl Config {
+    /// Config of the project in `directory`, or the default config if it has no config file.
+    pub fn load(directory: &Path) -> Result<Self, Error> {
+        let path = directory.join(CONFIG_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(anyhow!("Could not read {}: {}", path.display(), error)),
+        };
+
+        toml::from_str(&contents).map_err(|error| anyhow!("Invalid {}: {}", path.display(), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tags_per_package() {
+        let config: Config = toml::from_str(
+            r#"
+                [tags]
+                react = "next"
+                "@types/node" = "ts5.0"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.tags["react"], "next");
+        assert_eq!(config.tags["@types/node"], "ts5.0");
+        assert!(toml::from_str::<Config>("tag = \"next\"").is_err());
+    }
+
+    #[test]
+    fn reads_severity_colors() {
+        let config: Config = toml::from_str(
+            r#"
+                [severity]
+                "pre-1.0" = "Red"
+                minor = { Indexed = 208 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.severity.color(VersionSection::PreV1), Color::Red);
+        assert_eq!(
+            config.severity.color(VersionSection::Minor),
+            Color::Indexed(208)
+        );
+        assert_eq!(config.severity.color(VersionSection::Patch), Color::Green);
+        assert!(toml::from_str::<Config>("[severity]\nbreaking = \"Red\"").is_err());
+    }
+}
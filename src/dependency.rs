@@ -0,0 +1,741 @@
+//! A dependency of the project together with everything known about its update.
+
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    time::SystemTime,
+};
+
+use anyhow::{bail, Error};
+use node_semver::Version;
+use serde_json::Value;
+
+use crate::{
+    application::{DependencyTable, SortMode},
+    args::Args,
+    project::{OverridePin, Project},
+    registry::Metadata,
+    version::{self, UpdatePolicy, VersionSection},
+};
+
+/// What is known about a dependency once its registry metadata has been fetched.
+pub struct FetchedPackage {
+    /// Version that the dependency should be updated to.
+    pub target: String,
+    /// Newest version allowed by the declared range.
+    pub wanted: String,
+    /// Version tagged as `latest` in the registry.
+    pub latest: String,
+    /// Whether the declared version can still be installed from the registry.
+    pub is_published: bool,
+    /// Targets that can be chosen instead, see `UpdatePolicy::level_targets`.
+    pub level_targets: Vec<String>,
+    /// When the latest version was published, if the registry tells.
+    pub latest_published: Option<SystemTime>,
+    /// Version of the dist-tag that the dependency is declared with instead of a range, e.g. `beta`.
+    pub tag_version: Option<String>,
+    /// What is known for the other ranges that the package is declared with, by range, e.g. when `devDependencies`
+    /// allows other versions than `dependencies`.
+    pub other_ranges: HashMap<String, FetchedPackage>,
+}
+
+impl FetchedPackage {
+    pub fn new(metadata: &Metadata, declared: &str, update_policy: &UpdatePolicy) -> Self {
+        FetchedPackage {
+            target: update_policy.update_target(metadata, declared),
+            wanted: update_policy.wanted(metadata, declared),
+            latest: metadata.dist_tags.latest.clone(),
+            is_published: version::is_published(metadata, declared),
+            level_targets: update_policy.level_targets(metadata, declared),
+            latest_published: metadata.latest_published(),
+            tag_version: version::is_dist_tag(declared)
+                .then(|| metadata.dist_tags.get(declared.trim()))
+                .flatten()
+                .map(str::to_owned),
+            other_ranges: HashMap::new(),
+        }
+    }
+
+    /// What is known for each of the `ranges` that the package is declared with, in the order of `package.json`.
+    pub fn with_ranges(metadata: &Metadata, ranges: &[&str], update_policy: &UpdatePolicy) -> Self {
+        let (first, others) = ranges.split_first().unwrap_or((&"", &[]));
+        let mut fetched = FetchedPackage::new(metadata, first, update_policy);
+        for &range in others {
+            if range != *first && !fetched.other_ranges.contains_key(range) {
+                let other = FetchedPackage::new(metadata, range, update_policy);
+                fetched.other_ranges.insert(range.to_owned(), other);
+            }
+        }
+
+        fetched
+    }
+
+    /// What is known for the package as declared with `range`.
+    pub fn for_range(&self, range: &str) -> &FetchedPackage {
+        self.other_ranges.get(range).unwrap_or(self)
+    }
+
+    pub fn for_range_mut(&mut self, range: &str) -> &mut FetchedPackage {
+        if self.other_ranges.contains_key(range) {
+            return self.other_ranges.get_mut(range).unwrap();
+        }
+
+        self
+    }
+
+    /// Choose the next larger target among the level targets, or the next smaller one if `step` is negative.
+    ///
+    /// Stops at the smallest and largest target.
+    pub fn step_target(&mut self, step: isize) {
+        // Build metadata doesn't tell versions apart, so `1.0.0+b` is the same target as `1.0.0+a`
+        let target = Version::parse(&self.target).ok();
+        let current = self.level_targets.iter().position(|level_target| {
+            target.is_some() && Version::parse(level_target).ok() == target
+        });
+        let index = match current {
+            Some(index) => index.saturating_add_signed(step),
+            None if step < 0 => 0,
+            None => return,
+        };
+
+        if let Some(target) = self
+            .level_targets
+            .get(index.min(self.level_targets.len().saturating_sub(1)))
+        {
+            self.target = target.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+impl FetchedPackage {
+    /// Package whose target, wanted and latest versions are all `version`, without level targets.
+    pub fn at(version: &str) -> Self {
+        FetchedPackage {
+            target: version.to_owned(),
+            wanted: version.to_owned(),
+            latest: version.to_owned(),
+            is_published: true,
+            level_targets: Vec::new(),
+            latest_published: None,
+            tag_version: None,
+            other_ranges: HashMap::new(),
+        }
+    }
+}
+
+pub struct Dependency<'a> {
+    pub name: &'a str,
+    /// Version range declared in `package.json`.
+    pub declared: &'a str,
+    /// Lowest version allowed by the declared range, or the installed version when comparing those.
+    pub current: Option<Version>,
+    /// Whether `current` is the version installed in `node_modules`.
+    pub is_installed: bool,
+    pub kind: DependencyTable,
+    /// Version to update to, once it has been fetched from the registry.
+    pub latest: Option<&'a str>,
+    /// Registry information, once it has been fetched.
+    pub fetched: Option<&'a FetchedPackage>,
+    /// What the update changes, `None` if there is no known update.
+    pub severity: Option<VersionSection>,
+    /// Whether the dependency is marked for update.
+    pub marked: bool,
+    /// Whether the declared version has been unpublished from the registry.
+    pub unpublished: bool,
+    /// Whether the dependency is an optional peer, whose updates matter less.
+    pub optional: bool,
+    /// Whether the dependency ships inside the published package, so its update reaches users with the next release.
+    pub bundled: bool,
+    /// JSON pointer of an override pin, since the same package may be pinned in several places.
+    pub pointer: Option<String>,
+}
+
+impl<'a> Dependency<'a> {
+    /// Key that marks the dependency for update, the pointer of an override pin or the name otherwise.
+    pub fn key(&self) -> &str {
+        self.pointer.as_deref().unwrap_or(self.name)
+    }
+
+    /// Dependencies of a table in the order they are displayed.
+    pub fn list(
+        project: &'a Project,
+        selection: &'a Selection,
+        fetched_packages: &'a HashMap<String, FetchedPackage>,
+        kind: DependencyTable,
+        sort_mode: SortMode,
+        marked: &HashSet<String>,
+    ) -> Vec<Dependency<'a>> {
+        let pointers: Vec<_> = selection
+            .override_pins(project, kind)
+            .map(|pin| pin.pointer)
+            .collect();
+        let mut list: Vec<_> = selection
+            .dependencies(project, kind)
+            .enumerate()
+            .map(|(index, (name, declared))| {
+                let pointer = pointers.get(index).cloned();
+                let declared = declared.as_str().unwrap_or_default();
+                // Overrides pin nested copies, which the top-level install says nothing about
+                let installed = match kind {
+                    DependencyTable::Overrides => None,
+                    _ if selection.compares_installed => selection.installed.get(name.as_str()),
+                    _ => None,
+                };
+                let fetched = fetched_packages
+                    .get(name)
+                    .map(|fetched| fetched.for_range(declared));
+                // A dist-tag stands for the version it points to, which only the registry knows
+                let tag_version = fetched
+                    .and_then(|fetched| fetched.tag_version.as_deref())
+                    .and_then(|tag_version| Version::parse(tag_version).ok());
+                let current = installed
+                    .cloned()
+                    .or(tag_version)
+                    .or_else(|| version::current_version(declared));
+                let latest = fetched.map(|fetched| fetched.target.as_str());
+                let severity = current
+                    .as_ref()
+                    .zip(latest)
+                    .and_then(|(current, latest)| version::classify(current, latest));
+
+                Dependency {
+                    name,
+                    declared,
+                    current,
+                    is_installed: installed.is_some(),
+                    kind,
+                    latest,
+                    fetched,
+                    severity,
+                    marked: marked.contains(pointer.as_deref().unwrap_or(name)),
+                    unpublished: fetched.is_some_and(|fetched| !fetched.is_published),
+                    optional: kind == DependencyTable::Peer && project.is_optional_peer(name),
+                    bundled: kind != DependencyTable::Overrides && project.is_bundled(name),
+                    pointer,
+                }
+            })
+            .collect();
+
+        match sort_mode {
+            SortMode::Manifest => {}
+            // Stable sort keeps the manifest order within the same severity.
+            // `None` sorts before `Some`, so place dependencies without an update last explicitly.
+            SortMode::Severity => {
+                list.sort_by_key(|dependency| (dependency.severity.is_none(), dependency.severity))
+            }
+            // `Reverse(None)` sorts after every date, so packages not fetched yet come last
+            SortMode::LatestPublished => list.sort_by_key(|dependency| {
+                Reverse(
+                    dependency
+                        .fetched
+                        .and_then(|fetched| fetched.latest_published),
+                )
+            }),
+        }
+
+        list
+    }
+
+    /// Text of the current column, the installed version when it is compared or else the declared range, along
+    /// with the version it points to if it is a dist-tag.
+    pub fn current_label(&self) -> Cow<'a, str> {
+        match &self.current {
+            Some(current) if self.is_installed => Cow::Owned(current.to_string()),
+            Some(current) if version::is_dist_tag(self.declared) => {
+                Cow::Owned(format!("{} ({})", self.declared, current))
+            }
+            _ => Cow::Borrowed(self.declared),
+        }
+    }
+
+    pub fn is_workspace_link(&self) -> bool {
+        version::is_workspace_link(self.declared)
+    }
+
+    /// Whether the declared range allows the latest version, `None` if that's unknown.
+    pub fn allows_latest(&self) -> Option<bool> {
+        match self.fetched {
+            Some(fetched) if !self.is_workspace_link() => {
+                version::allows(self.declared, &fetched.latest)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether there is an update that is at most as severe as `up_to`, e.g. a minor or patch update for `minor`.
+    /// Any update is included without a limit.
+    pub fn has_update_up_to(&self, up_to: Option<VersionSection>) -> bool {
+        match (self.severity, up_to) {
+            // Variants are ordered from the most severe one
+            (Some(severity), Some(up_to)) => severity >= up_to,
+            (severity, _) => severity.is_some(),
+        }
+    }
+
+    /// The dependency with its update limited to at most `up_to`, e.g. to the newest minor or patch update of a
+    /// package with a major update for `minor`. `None` if there is no such update, any update is kept without a limit.
+    pub fn with_update_up_to(mut self, up_to: Option<VersionSection>) -> Option<Self> {
+        if self.has_update_up_to(up_to) {
+            return Some(self);
+        }
+        let (up_to, current, fetched) = (up_to?, self.current.as_ref()?, self.fetched?);
+
+        let (target, severity) = fetched.level_targets.iter().rev().find_map(|target| {
+            let severity = version::classify(current, target)?;
+            (severity >= up_to).then_some((target, severity))
+        })?;
+        self.latest = Some(target);
+        self.severity = Some(severity);
+
+        Some(self)
+    }
+
+    /// Whether the declared range already starts at the latest version.
+    pub fn is_up_to_date(&self) -> bool {
+        match (&self.current, self.latest) {
+            (Some(current), Some(latest)) => version::is_up_to_date(current, latest),
+            _ => false,
+        }
+    }
+}
+
+/// Dependencies selected with command line arguments.
+#[derive(Clone)]
+pub struct Selection {
+    /// Dependency tables to check.
+    pub tables: Vec<DependencyTable>,
+    /// Packages to check, all packages if empty.
+    packages: HashSet<String>,
+    /// Packages left out even though they are listed, e.g. ones whose target is given with `--set`.
+    excluded: HashSet<String>,
+    /// Versions installed in `node_modules`, by package name.
+    installed: HashMap<String, Version>,
+    /// Whether updates are compared against the installed versions rather than the declared ranges.
+    pub compares_installed: bool,
+    /// Whether peer dependencies are updated rather than only shown, see `--update-peers`.
+    updates_peers: bool,
+}
+
+impl Selection {
+    pub fn new(args: &Args, project: &Project) -> Result<Selection, Error> {
+        let mut selection = Selection {
+            tables: args.tables(),
+            packages: args.packages.iter().cloned().collect(),
+            excluded: HashSet::new(),
+            installed: HashMap::new(),
+            compares_installed: args.installed,
+            updates_peers: args.update_peers,
+        };
+
+        for package in &args.packages {
+            let is_listed = selection.tables.iter().any(|&table| {
+                selection
+                    .dependencies(project, table)
+                    .any(|(name, _)| name == package)
+            });
+            if !is_listed {
+                bail!("{} is not a dependency in package.json", package);
+            }
+        }
+
+        // Loaded even when comparing declared ranges, since the interactive UI switches between both
+        selection.installed = selection
+            .names(project)
+            .filter_map(|name| Some((name.clone(), project.installed_version(name)?)))
+            .collect();
+
+        Ok(selection)
+    }
+
+    /// Whether the updates of a table are applied and count as outdated, rather than only being shown.
+    pub fn updates(&self, table: DependencyTable) -> bool {
+        self.tables.contains(&table) && (table != DependencyTable::Peer || self.updates_peers)
+    }
+
+    pub fn includes(&self, name: &str) -> bool {
+        !self.excluded.contains(name) && (self.packages.is_empty() || self.packages.contains(name))
+    }
+
+    /// The same selection without the given packages.
+    pub fn without<'a>(&self, names: impl IntoIterator<Item = &'a String>) -> Selection {
+        let mut selection = self.clone();
+        selection.excluded.extend(names.into_iter().cloned());
+
+        selection
+    }
+
+    /// Selected dependencies of a table with their declared versions, in the order of `package.json`.
+    pub fn dependencies<'a>(
+        &'a self,
+        project: &'a Project,
+        table: DependencyTable,
+    ) -> impl Iterator<Item = (&'a String, &'a Value)> + 'a {
+        // Overrides nest pins under the packages they apply to, so they are not a flat group
+        let group = match table {
+            DependencyTable::Overrides => None,
+            _ => project.dependency_group(table.key()),
+        };
+
+        group
+            .into_iter()
+            .flatten()
+            .filter(move |_| self.tables.contains(&table))
+            .filter(|(name, _)| self.includes(name))
+            .chain(
+                self.override_pins(project, table)
+                    .map(|pin| (pin.name, pin.version)),
+            )
+    }
+
+    /// Selected override pins when `table` is the `Overrides` table, in the order of `dependencies`.
+    pub fn override_pins<'a>(
+        &'a self,
+        project: &'a Project,
+        table: DependencyTable,
+    ) -> impl Iterator<Item = OverridePin<'a>> + 'a {
+        let pins = match table {
+            DependencyTable::Overrides if self.tables.contains(&table) => project.override_pins(),
+            _ => Vec::new(),
+        };
+
+        pins.into_iter().filter(|pin| self.includes(pin.name))
+    }
+
+    /// Ranges that a selected dependency is declared or pinned with in all tables, in the order of the tables.
+    pub fn declared_ranges<'a>(&'a self, project: &'a Project, name: &str) -> Vec<&'a str> {
+        self.tables
+            .iter()
+            .flat_map(|&table| self.dependencies(project, table))
+            .filter(|(listed, _)| *listed == name)
+            .filter_map(|(_, range)| range.as_str())
+            .collect()
+    }
+
+    /// Names of the selected dependencies of all tables that need to be fetched, i.e. all but workspace links.
+    ///
+    /// A package listed in several tables is only named once, since its metadata applies to all of them.
+    pub fn names<'a>(&'a self, project: &'a Project) -> impl Iterator<Item = &'a String> + 'a {
+        let mut seen = HashSet::new();
+
+        self.tables
+            .iter()
+            .flat_map(|&table| self.dependencies(project, table))
+            .filter(|(_, declared)| {
+                !version::is_workspace_link(declared.as_str().unwrap_or_default())
+            })
+            .map(|(name, _)| name)
+            .filter(move |name| seen.insert(*name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn names_packages_in_several_tables_once() {
+        let project = Project::parse(
+            r#"{
+                "dependencies": { "react": "^17.0.2", "lodash": "^4.17.0" },
+                "devDependencies": { "eslint": "^8.0.0", "lodash": "^4.17.21" }
+            }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+
+        assert_eq!(
+            selection.names(&project).collect::<Vec<_>>(),
+            ["react", "lodash", "eslint"]
+        );
+    }
+
+    #[test]
+    fn sorts_by_latest_publish_date() {
+        let project = Project::parse(
+            r#"{ "dependencies": { "react": "^17.0.2", "lodash": "^4.17.0", "chalk": "^4.0.0" } }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat", "--sort", "latest-desc"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        let fetched = |metadata: serde_json::Value| {
+            let metadata: Metadata = serde_json::from_value(metadata).unwrap();
+            FetchedPackage::new(&metadata, "^1.0.0", &UpdatePolicy::default())
+        };
+        let fetched_packages = HashMap::from([
+            (
+                "react".to_owned(),
+                fetched(serde_json::json!({
+                    "name": "react",
+                    "dist-tags": { "latest": "18.2.0" },
+                    "time": { "18.2.0": "2022-06-14T19:46:38.369Z", "18.3.0-canary": "2024-04-01T00:00:00.000Z" },
+                })),
+            ),
+            (
+                "lodash".to_owned(),
+                fetched(serde_json::json!({
+                    "name": "lodash",
+                    "dist-tags": { "latest": "4.17.21" },
+                    "modified": "2023-01-01T00:00:00.000Z",
+                })),
+            ),
+        ]);
+
+        let names: Vec<&str> = Dependency::list(
+            &project,
+            &selection,
+            &fetched_packages,
+            DependencyTable::Runtime,
+            args.sort,
+            &HashSet::new(),
+        )
+        .iter()
+        .map(|dependency| dependency.name)
+        .collect();
+        assert_eq!(names, ["lodash", "react", "chalk"]);
+    }
+
+    #[test]
+    fn compares_installed_versions_when_asked_to() {
+        let directory =
+            std::env::temp_dir().join(format!("packrat-installed-{}", std::process::id()));
+        let installed = directory.join("node_modules").join("@types").join("node");
+        fs::create_dir_all(&installed).unwrap();
+        fs::write(
+            installed.join("package.json"),
+            r#"{ "version": "18.11.0" }"#,
+        )
+        .unwrap();
+        let path = directory.join("package.json");
+        fs::write(
+            &path,
+            r#"{ "dependencies": { "@types/node": "^18.0.0", "lodash": "^4.17.0" } }"#,
+        )
+        .unwrap();
+        let project = Project::new(&path).unwrap();
+        let fetched_packages = HashMap::from([
+            ("@types/node".to_owned(), FetchedPackage::at("18.11.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.21")),
+        ]);
+
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        let mut selection = Selection::new(&args, &project).unwrap();
+        let list = |selection: &Selection| {
+            Dependency::list(
+                &project,
+                selection,
+                &fetched_packages,
+                DependencyTable::Runtime,
+                SortMode::Manifest,
+                &HashSet::new(),
+            )
+            .iter()
+            .map(|dependency| (dependency.current_label().into_owned(), dependency.severity))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            list(&selection),
+            [
+                ("^18.0.0".to_owned(), Some(VersionSection::Minor)),
+                ("^4.17.0".to_owned(), Some(VersionSection::Patch))
+            ]
+        );
+
+        // lodash is not installed, so its declared range is compared instead
+        selection.compares_installed = true;
+        assert_eq!(
+            list(&selection),
+            [
+                ("18.11.0".to_owned(), None),
+                ("^4.17.0".to_owned(), Some(VersionSection::Patch))
+            ]
+        );
+        let args = Args::try_parse_from(["packrat", "--installed"]).unwrap();
+        assert!(Selection::new(&args, &project).unwrap().compares_installed);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn resolves_dependencies_declared_with_a_dist_tag() {
+        let project = Project::parse(r#"{ "dependencies": { "react": "next" } }"#).unwrap();
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        let metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "name": "react",
+            "dist-tags": { "latest": "18.2.0", "next": "17.1.0" },
+        }))
+        .unwrap();
+        let fetched_packages = HashMap::from([(
+            "react".to_owned(),
+            FetchedPackage::new(&metadata, "next", &UpdatePolicy::default()),
+        )]);
+
+        let dependencies = Dependency::list(
+            &project,
+            &selection,
+            &fetched_packages,
+            DependencyTable::Runtime,
+            SortMode::Manifest,
+            &HashSet::new(),
+        );
+        assert_eq!(dependencies[0].current_label(), "next (17.1.0)");
+        assert_eq!(dependencies[0].severity, Some(VersionSection::Major));
+    }
+
+    #[test]
+    fn limits_updates_up_to_a_severity() {
+        let project = Project::parse(
+            r#"{ "dependencies": { "react": "^17.0.2", "chalk": "^0.4.0", "eslint": "^8.0.0", "lodash": "^4.17.0" } }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat", "--update-up-to", "minor"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        let react = FetchedPackage {
+            level_targets: ["17.0.3", "17.3.0", "18.2.0"].map(str::to_owned).to_vec(),
+            ..FetchedPackage::at("18.2.0")
+        };
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), react),
+            ("chalk".to_owned(), FetchedPackage::at("0.5.0")),
+            ("eslint".to_owned(), FetchedPackage::at("8.57.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.21")),
+        ]);
+        let dependencies = Dependency::list(
+            &project,
+            &selection,
+            &fetched_packages,
+            DependencyTable::Runtime,
+            SortMode::Manifest,
+            &HashSet::new(),
+        );
+
+        let names = |up_to| {
+            dependencies
+                .iter()
+                .filter(|dependency| dependency.has_update_up_to(up_to))
+                .map(|dependency| dependency.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(args.update_up_to), ["eslint", "lodash"]);
+        assert_eq!(
+            names(Some(VersionSection::PreV1)),
+            ["chalk", "eslint", "lodash"]
+        );
+        assert_eq!(names(None), ["react", "chalk", "eslint", "lodash"]);
+
+        // Packages whose latest version is too severe are limited to their newest allowed target instead
+        let limited = |up_to| {
+            Dependency::list(
+                &project,
+                &selection,
+                &fetched_packages,
+                DependencyTable::Runtime,
+                SortMode::Manifest,
+                &HashSet::new(),
+            )
+            .into_iter()
+            .filter_map(|dependency| dependency.with_update_up_to(up_to))
+            .map(|dependency| (dependency.name, dependency.latest.unwrap()))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            limited(args.update_up_to),
+            [
+                ("react", "17.3.0"),
+                ("eslint", "8.57.0"),
+                ("lodash", "4.17.21")
+            ]
+        );
+        assert_eq!(
+            limited(Some(VersionSection::Patch)),
+            [("react", "17.0.3"), ("lodash", "4.17.21")]
+        );
+    }
+
+    #[test]
+    fn computes_targets_from_each_declared_range() {
+        let project = Project::parse(
+            r#"{ "dependencies": { "react": "^17.0.2" }, "devDependencies": { "react": "^16.0.0" } }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat", "--in-range"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        let metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "name": "react",
+            "dist-tags": { "latest": "18.2.0" },
+            "versions": { "16.0.0": {}, "16.14.0": {}, "17.0.2": {}, "17.1.0": {}, "18.2.0": {} },
+        }))
+        .unwrap();
+        let update_policy = UpdatePolicy {
+            in_range: true,
+            ..UpdatePolicy::default()
+        };
+        let ranges = selection.declared_ranges(&project, "react");
+        assert_eq!(ranges, ["^17.0.2", "^16.0.0"]);
+        let fetched_packages = HashMap::from([(
+            "react".to_owned(),
+            FetchedPackage::with_ranges(&metadata, &ranges, &update_policy),
+        )]);
+
+        let latest = |table| {
+            Dependency::list(
+                &project,
+                &selection,
+                &fetched_packages,
+                table,
+                SortMode::Manifest,
+                &HashSet::new(),
+            )[0]
+            .latest
+        };
+        assert_eq!(latest(DependencyTable::Runtime), Some("17.1.0"));
+        assert_eq!(latest(DependencyTable::Dev), Some("16.14.0"));
+    }
+
+    #[test]
+    fn only_shows_peer_dependencies_unless_asked_to_update_them() {
+        let project = Project::parse(r#"{ "peerDependencies": { "react": "^17.0.0" } }"#).unwrap();
+        let selection = |args: &[&str]| {
+            let args = Args::try_parse_from([&["packrat"], args].concat()).unwrap();
+            Selection::new(&args, &project).unwrap()
+        };
+
+        assert!(selection(&[]).updates(DependencyTable::Runtime));
+        assert!(!selection(&[]).updates(DependencyTable::Peer));
+        assert!(selection(&["--update-peers"]).updates(DependencyTable::Peer));
+        assert!(!selection(&["--prod-only"]).updates(DependencyTable::Dev));
+    }
+
+    #[test]
+    fn never_names_dev_dependencies_with_prod_only() {
+        let project = Project::parse(
+            r#"{
+                "dependencies": { "react": "^17.0.2", "lodash": "^4.17.0" },
+                "devDependencies": { "eslint": "^8.0.0", "lodash": "^4.17.21" }
+            }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat", "--prod-only"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+
+        assert_eq!(
+            selection.names(&project).collect::<Vec<_>>(),
+            ["react", "lodash"]
+        );
+        assert_eq!(
+            selection
+                .dependencies(&project, DependencyTable::Dev)
+                .count(),
+            0
+        );
+
+        let args = Args::try_parse_from(["packrat", "--prod-only", "eslint"]).unwrap();
+        assert!(Selection::new(&args, &project).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Release notes of packages hosted on GitHub, only fetched with `--github-token` since the API is rate limited.
+
+use anyhow::{bail, Error};
+use node_semver::Version;
+use reqwest::{header::ACCEPT, Client, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::registry::{self, Registries};
+
+const API_URL: &str = "https://api.github.com";
+/// Releases fetched per package, GitHub doesn't return more in a single page.
+const RELEASES_PER_PAGE: usize = 100;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Repository {
+    pub owner: String,
+    pub name: String,
+}
+
+impl Repository {
+    /// GitHub repository in the `repository` field of a package, e.g. `git+https://github.com/facebook/react.git`,
+    /// `github:facebook/react` or `{ "url": "...", "directory": "packages/react" }`.
+    pub fn parse(repository: &Value) -> Option<Self> {
+        let url = repository.as_str().or_else(|| repository["url"].as_str())?;
+        let path = match url.split_once("github.com") {
+            Some((_, path)) => path.trim_start_matches(['/', ':']),
+            None => url.strip_prefix("github:")?,
+        };
+        let mut parts = path.trim_end_matches(".git").split('/');
+
+        match (parts.next(), parts.next()) {
+            (Some(owner), Some(name)) if !owner.is_empty() && !name.is_empty() => {
+                Some(Repository {
+                    owner: owner.to_owned(),
+                    name: name.to_owned(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Release {
+    pub tag_name: String,
+    /// Title of the release, GitHub shows the tag instead if it's missing.
+    pub name: Option<String>,
+    pub body: Option<String>,
+}
+
+impl Release {
+    pub fn title(&self) -> &str {
+        match self.name.as_deref() {
+            Some(name) if !name.trim().is_empty() => name,
+            _ => &self.tag_name,
+        }
+    }
+
+    /// Version of the release of `package_name`, from tags like `v18.2.0` or, in monorepos, `react@18.2.0`.
+    fn version(&self, package_name: &str) -> Option<Version> {
+        let version = match self.tag_name.rsplit_once('@') {
+            Some((tag_package, version)) if tag_package == package_name => version,
+            Some(_) => return None,
+            None => &self.tag_name,
+        };
+
+        Version::parse(version.trim_start_matches('v')).ok()
+    }
+}
+
+/// HTTP client for the GitHub API, which always verifies certificates since requests carry the `--github-token`.
+///
+/// `--insecure` and `--ca-cert` only apply to registries, so they are not taken into account here.
+pub fn client(user_agent: &str) -> Result<Client, reqwest::Error> {
+    Client::builder().user_agent(user_agent).build()
+}
+
+/// Releases of a package after the `current` version up to the `latest` one, newest first.
+///
+/// The repository is looked up with the `registry_client`, and the releases fetched with the `github_client`.
+pub async fn release_notes(
+    registry_client: &Client,
+    github_client: &Client,
+    registries: &Registries,
+    token: &str,
+    package_name: &str,
+    current: &Version,
+    latest: &Version,
+) -> Result<Vec<Release>, Error> {
+    let repository = registry::fetch_repository(registry_client, registries, package_name).await?;
+    let repository = match Repository::parse(&repository) {
+        Some(repository) => repository,
+        None => bail!("{} is not hosted on GitHub", package_name),
+    };
+
+    let url = format!(
+        "{}/repos/{}/{}/releases?per_page={}",
+        API_URL, repository.owner, repository.name, RELEASES_PER_PAGE
+    );
+    let response = github_client
+        .get(url)
+        .bearer_auth(token)
+        .header(ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?;
+    match response.status() {
+        StatusCode::UNAUTHORIZED => bail!("GitHub rejected the token of --github-token"),
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS => {
+            bail!("GitHub rate limit reached, try again later")
+        }
+        StatusCode::NOT_FOUND => bail!(
+            "{}/{} was not found on GitHub",
+            repository.owner,
+            repository.name
+        ),
+        _ => {}
+    }
+    let releases: Vec<Release> = response.error_for_status()?.json().await?;
+
+    Ok(releases_between(releases, package_name, current, latest))
+}
+
+fn releases_between(
+    releases: Vec<Release>,
+    package_name: &str,
+    current: &Version,
+    latest: &Version,
+) -> Vec<Release> {
+    let mut releases: Vec<(Version, Release)> = releases
+        .into_iter()
+        .filter_map(|release| Some((release.version(package_name)?, release)))
+        .filter(|(version, _)| version > current && version <= latest)
+        .collect();
+    releases.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    releases.into_iter().map(|(_, release)| release).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_github_repositories() {
+        let react = Some(Repository {
+            owner: "facebook".to_owned(),
+            name: "react".to_owned(),
+        });
+        for repository in [
+            json!("git+https://github.com/facebook/react.git"),
+            json!("github:facebook/react"),
+            json!("git@github.com:facebook/react.git"),
+            json!({ "type": "git", "url": "https://github.com/facebook/react.git", "directory": "packages/react" }),
+        ] {
+            assert_eq!(Repository::parse(&repository), react, "{}", repository);
+        }
+
+        assert_eq!(
+            Repository::parse(&json!("https://gitlab.com/foo/bar")),
+            None
+        );
+        assert_eq!(Repository::parse(&Value::Null), None);
+    }
+
+    #[test]
+    fn keeps_releases_between_current_and_latest() {
+        let release = |tag_name: &str| Release {
+            tag_name: tag_name.to_owned(),
+            name: None,
+            body: None,
+        };
+        let releases = vec![
+            release("v17.0.2"),
+            release("v18.0.0"),
+            release("react@18.2.0"),
+            release("react-dom@18.1.0"),
+            release("v18.3.0-canary"),
+            release("nightly"),
+        ];
+        let current = Version::parse("17.0.2").unwrap();
+        let latest = Version::parse("18.2.0").unwrap();
+
+        let tags: Vec<String> = releases_between(releases, "react", &current, &latest)
+            .into_iter()
+            .map(|release| release.tag_name)
+            .collect();
+        assert_eq!(tags, ["react@18.2.0", "v18.0.0"]);
+    }
+}
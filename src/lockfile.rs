@@ -0,0 +1,181 @@
+//! Read the installed dependency graph of a project from its `package-lock.json`.
+//!
+//! Both the `packages` layout of lockfile versions 2 and 3 and the nested `dependencies` layout of version 1
+//! are supported. Packages are identified by name only, so different installed versions of a package share
+//! their dependents. The direct dependencies come from `package.json`, since the root `dependencies` of a version 1
+//! lockfile list every hoisted package.
+
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fs,
+    path::Path,
+};
+
+use serde_json::{Map, Value};
+
+use crate::project::Project;
+
+/// Fields of a lockfile entry that list the dependencies of a package.
+const DEPENDENCY_FIELDS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+pub struct Lockfile {
+    /// Names of the packages that depend on a package, keyed by the package name.
+    dependents: HashMap<String, BTreeSet<String>>,
+    /// Names of the packages that the project itself depends on.
+    direct: BTreeSet<String>,
+}
+
+impl Lockfile {
+    /// Read `package-lock.json` in the given directory, if there is a valid one.
+    pub fn load(directory: &Path, project: &Project) -> Option<Lockfile> {
+        let contents = fs::read_to_string(directory.join("package-lock.json")).ok()?;
+
+        Lockfile::parse(&contents, project).ok()
+    }
+
+    /// Parse the contents of the lockfile of `project`.
+    pub fn parse(contents: &str, project: &Project) -> serde_json::Result<Lockfile> {
+        let values: Value = serde_json::from_str(contents)?;
+        let mut lockfile = Lockfile {
+            dependents: HashMap::new(),
+            direct: DEPENDENCY_FIELDS
+                .iter()
+                .filter_map(|field| project.dependency_group(field))
+                .flat_map(|dependencies| dependencies.keys().cloned())
+                .collect(),
+        };
+
+        if let Some(packages) = values["packages"].as_object() {
+            for (path, package) in packages {
+                // The root project is stored under an empty path
+                if path.is_empty() {
+                    continue;
+                }
+                let name = path.rsplit("node_modules/").next().unwrap_or_default();
+                lockfile.add_package(name, package, &DEPENDENCY_FIELDS);
+            }
+        } else if let Some(dependencies) = values["dependencies"].as_object() {
+            lockfile.add_v1_dependencies(dependencies);
+        }
+
+        Ok(lockfile)
+    }
+
+    fn add_package(&mut self, name: &str, package: &Value, fields: &[&str]) {
+        let dependencies = fields
+            .iter()
+            .filter_map(|field| package[field].as_object())
+            .flat_map(|dependencies| dependencies.keys());
+
+        for dependency in dependencies {
+            self.dependents
+                .entry(dependency.to_owned())
+                .or_default()
+                .insert(name.to_owned());
+        }
+    }
+
+    /// Version 1 lockfiles nest packages under `dependencies` and list their own dependencies under `requires`.
+    fn add_v1_dependencies(&mut self, dependencies: &Map<String, Value>) {
+        for (name, package) in dependencies {
+            self.add_package(name, package, &["requires"]);
+            if let Some(nested) = package["dependencies"].as_object() {
+                self.add_v1_dependencies(nested);
+            }
+        }
+    }
+
+    /// Packages that directly depend on the given package.
+    pub fn dependents(&self, name: &str) -> Vec<&str> {
+        self.dependents
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Direct dependencies of the project that pull in the given package, possibly through other packages.
+    pub fn direct_dependents<'a>(&'a self, name: &'a str) -> Vec<&'a str> {
+        let mut visited = BTreeSet::from([name]);
+        let mut queue = VecDeque::from([name]);
+        let mut direct = BTreeSet::new();
+
+        while let Some(package) = queue.pop_front() {
+            for dependent in self.dependents(package) {
+                if visited.insert(dependent) {
+                    queue.push_back(dependent);
+                }
+            }
+            if package != name && self.direct.contains(package) {
+                direct.insert(package);
+            }
+        }
+
+        direct.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_direct_dependents_through_transitive_packages() {
+        let project = Project::parse(
+            r#"{ "dependencies": { "react": "^18.0.0" }, "devDependencies": { "jest": "^29.0.0" } }"#,
+        )
+        .unwrap();
+        let lockfile = Lockfile::parse(
+            r#"{
+                "lockfileVersion": 3,
+                "packages": {
+                    "": { "dependencies": { "react": "^18.0.0" }, "devDependencies": { "jest": "^29.0.0" } },
+                    "node_modules/react": { "dependencies": { "loose-envify": "^1.1.0" } },
+                    "node_modules/jest": { "dependencies": { "@jest/core": "^29.0.0" } },
+                    "node_modules/@jest/core": { "dependencies": { "loose-envify": "^1.0.0" } },
+                    "node_modules/loose-envify": { "dependencies": { "js-tokens": "^4.0.0" } }
+                }
+            }"#,
+            &project,
+        )
+        .unwrap();
+
+        assert_eq!(lockfile.dependents("loose-envify"), ["@jest/core", "react"]);
+        assert_eq!(lockfile.direct_dependents("js-tokens"), ["jest", "react"]);
+        assert!(lockfile.direct_dependents("react").is_empty());
+    }
+
+    #[test]
+    fn reads_direct_dependencies_of_v1_lockfiles_from_package_json() {
+        let project = Project::parse(r#"{ "dependencies": { "react": "^16.0.0" } }"#).unwrap();
+        // Every hoisted package is listed at the root of a version 1 lockfile
+        let lockfile = Lockfile::parse(
+            r#"{
+                "lockfileVersion": 1,
+                "dependencies": {
+                    "react": { "version": "16.14.0", "requires": { "loose-envify": "^1.1.0", "prop-types": "^15.6.2" } },
+                    "prop-types": {
+                        "version": "15.8.1",
+                        "requires": { "loose-envify": "^1.4.0" },
+                        "dependencies": { "react-is": { "version": "16.13.1" } }
+                    },
+                    "loose-envify": { "version": "1.4.0", "requires": { "js-tokens": "^4.0.0" } },
+                    "js-tokens": { "version": "4.0.0" }
+                }
+            }"#,
+            &project,
+        )
+        .unwrap();
+
+        assert_eq!(lockfile.dependents("loose-envify"), ["prop-types", "react"]);
+        assert_eq!(lockfile.direct_dependents("js-tokens"), ["react"]);
+        // prop-types is only hoisted, so it doesn't count as a direct dependency
+        assert_eq!(lockfile.direct_dependents("loose-envify"), ["react"]);
+    }
+}
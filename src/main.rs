@@ -1,5 +1,12 @@
+use std::{
+    io::{self, stdout},
+    process::ExitCode,
+};
+
 use anyhow::Error;
 use clap::Parser;
+use crossterm::tty::IsTty;
+use tokio::runtime::{Builder, Runtime};
 
 use application::Application;
 
@@ -7,18 +14,64 @@ use crate::args::Args;
 
 mod application;
 mod args;
+mod cache;
+mod config;
+mod dependency;
+mod github;
+mod lockfile;
 mod project;
 mod registry;
+mod report;
+mod text;
 mod ui;
+mod update;
+mod version;
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
+fn main() -> Result<ExitCode, Error> {
     let args = Args::parse();
+    runtime(args.threads)?.block_on(run(args))
+}
+
+/// Runtime with the `--threads` of the arguments, one worker thread for 1 and one per CPU by default.
+fn runtime(threads: Option<usize>) -> io::Result<Runtime> {
+    let mut builder = match threads {
+        Some(1) => Builder::new_current_thread(),
+        Some(threads) => {
+            let mut builder = Builder::new_multi_thread();
+            builder.worker_threads(threads);
+            builder
+        }
+        None => Builder::new_multi_thread(),
+    };
+    builder.enable_all().build()
+}
+
+async fn run(args: Args) -> Result<ExitCode, Error> {
+    if args.log_connections {
+        registry::log_connections();
+    }
+    if args.insecure {
+        eprintln!("⚠ {}", registry::INSECURE_WARNING);
+    }
+    if args.fix_duplicates {
+        return update::fix_duplicates(args);
+    }
+    if args.is_batch_update() {
+        return update::run(args).await;
+    }
+    if args.diff_cache {
+        return report::diff_cache(args).await;
+    }
+    // The interactive UI cannot be drawn into a pipe or a file, so print a report there instead
+    if args.is_report() || !stdout().is_tty() {
+        return report::run(args).await;
+    }
+
     let res = Application::new(args)?.run().await;
 
     if let Err(error) = res {
         eprintln!("{:?}", error)
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -1,79 +1,1602 @@
 use std::{
-    fs::File,
-    io::{Read, Write},
-    path::PathBuf,
+    cmp::Reverse,
+    collections::HashSet,
+    fmt,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
 };
 
-use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use node_semver::{Range, Version};
+use serde::{
+    de::{DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserializer,
+};
+use serde_json::{error::Category, Map, Value};
+
+/// UTF-8 byte order mark that some Windows editors put at the start of files.
+const BOM: char = '\u{feff}';
+
+/// Version of a tool, e.g. a package manager, that the project pins outside of its dependencies.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PinnedTool {
+    pub name: String,
+    pub version: String,
+    /// Field that pins the tool, e.g. `packageManager` or `volta.pnpm`.
+    pub field: String,
+}
+
+/// Version pinned by npm `overrides`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Override {
+    /// Packages leading to the pinned one, e.g. `["bar", "baz"]` for `{ "bar": { "baz": "1.0.0" } }`.
+    pub path: Vec<String>,
+    pub version: String,
+    /// JSON pointer of the pinned version in `package.json`.
+    pub pointer: String,
+}
+
+/// Version pinned by `overrides` that is fetched and updated like a dependency, see `Project::override_pins`.
+pub struct OverridePin<'a> {
+    /// Name of the pinned package.
+    pub name: &'a String,
+    pub version: &'a Value,
+    /// JSON pointer of the pinned version in `package.json`, which tells apart pins of the same package.
+    pub pointer: String,
+}
+
+/// Why a `package.json` file could not be read, with its path since the error is usually the first thing shown.
+#[derive(Debug)]
+pub enum Error {
+    NotFound(PathBuf),
+    PermissionDenied(PathBuf),
+    IsADirectory(PathBuf),
+    Read(PathBuf, io::Error),
+    Json(PathBuf, serde_json::Error),
+    /// Valid JSON, but not an object of fields, e.g. an array.
+    NotAnObject(PathBuf),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(path) => write!(f, "{} not found", path.display()),
+            Error::PermissionDenied(path) => write!(
+                f,
+                "{} is not readable by the current user, check its permissions",
+                path.display()
+            ),
+            Error::IsADirectory(path) => write!(
+                f,
+                "{} is a directory, expected a package.json file",
+                path.display()
+            ),
+            Error::Read(path, e) => write!(f, "Unable to read {}: {}", path.display(), e),
+            Error::NotAnObject(path) => write!(f, "{} root is not an object", path.display()),
+            Error::Json(path, e) => {
+                write!(f, "{}:{}:{}: ", path.display(), e.line(), e.column())?;
+                match e.classify() {
+                    // Usually a file that is still being edited
+                    Category::Eof => f.write_str(
+                        "the JSON ends unexpectedly, is a closing brace or bracket missing?",
+                    ),
+                    _ => {
+                        // The position is already shown in front of the message
+                        let message = e.to_string();
+                        let message = match message.rsplit_once(" at line ") {
+                            Some((message, _)) => message.to_owned(),
+                            None => message,
+                        };
+                        write!(f, "invalid JSON, {}", message)
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
 
 /// A structure that represents a project with values from its `package.json` file.
-#[derive(Deserialize, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
+///
+/// Fields are looked up by their exact keys in `values`, so that writing never renames or adds a key.
+#[derive(Clone)]
 pub struct Project {
     pub values: Value,
+    /// File the project was read from and that updates are written to.
+    path: PathBuf,
+    /// Whether the file started with a byte order mark, which is kept on write.
+    bom: bool,
+    /// Whether the file used CRLF line endings, which are kept on write.
+    crlf: bool,
+    /// Paths of keys listed more than once in the same object, e.g. `dependencies.react`. Only the last one is kept.
+    duplicate_keys: Vec<String>,
+    /// Version ranges of the `npm:` imports of a `deno.json` file by package name, which stand in for its
+    /// dependencies. `None` for a `package.json` file.
+    npm_imports: Option<Map<String, Value>>,
+    /// Original contents and `imports` of a Deno file with comments or trailing commas, which serializing would drop.
+    /// Changed imports are replaced in the original contents instead, see `to_json_string`.
+    jsonc: Option<(String, Value)>,
 }
 
 impl Project {
-    pub fn new(path: &PathBuf) -> std::io::Result<Project> {
-        let mut raw_package_json = File::open(path)?;
+    pub fn new(path: &PathBuf) -> Result<Project, Error> {
+        let read_error = |e: io::Error| match e.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(path.clone()),
+            // Opening a directory succeeds on some platforms and is denied on others, only reading it fails everywhere
+            _ if path.is_dir() => Error::IsADirectory(path.clone()),
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(path.clone()),
+            _ => Error::Read(path.clone(), e),
+        };
+        let mut raw_package_json = File::open(path).map_err(read_error)?;
         let mut contents = String::new();
-        raw_package_json.read_to_string(&mut contents)?;
-        let values: Value = serde_json::from_str(&contents)?;
+        raw_package_json
+            .read_to_string(&mut contents)
+            .map_err(read_error)?;
 
-        Ok(Project { values })
+        let parse = match path.file_name() {
+            Some(file_name) if file_name == "deno.json" || file_name == "deno.jsonc" => {
+                Project::parse_deno
+            }
+            _ => Project::parse,
+        };
+        let mut project = parse(&contents).map_err(|e| Error::Json(path.clone(), e))?;
+        // Fields of anything else read as missing, and updating them would panic
+        if !project.values.is_object() {
+            return Err(Error::NotAnObject(path.clone()));
+        }
+        project.path = path.clone();
+
+        Ok(project)
+    }
+
+    /// Parse the contents of a `package.json` file.
+    pub fn parse(contents: &str) -> serde_json::Result<Project> {
+        let bom = contents.starts_with(BOM);
+        let contents = contents.trim_start_matches(BOM);
+        let crlf = contents.contains("\r\n");
+        let values: Value = serde_json::from_str(contents)?;
+
+        Ok(Project {
+            values,
+            path: PathBuf::new(),
+            bom,
+            crlf,
+            duplicate_keys: duplicate_keys(contents),
+            npm_imports: None,
+            jsonc: None,
+        })
+    }
+
+    /// Parse the contents of a `deno.json` or `deno.jsonc` file, whose `imports` with an `npm:` specifier are its
+    /// dependencies. Deno allows comments and trailing commas in both.
+    ///
+    /// Other imports, e.g. `jsr:` or URLs, don't come from the npm registry and are left out, as are `npm:` imports
+    /// without a version. A package imported several times, e.g. also with a trailing `/`, is listed once.
+    pub fn parse_deno(contents: &str) -> serde_json::Result<Project> {
+        let json = strip_jsonc(contents);
+        let mut project = Project::parse(&json)?;
+        if json != contents {
+            project.jsonc = Some((contents.to_owned(), project.values["imports"].clone()));
+        }
+        let mut npm_imports = Map::new();
+        for specifier in project.values["imports"]
+            .as_object()
+            .into_iter()
+            .flat_map(Map::values)
+            .filter_map(Value::as_str)
+        {
+            if let Some((name, range)) = npm_specifier(specifier) {
+                if !npm_imports.contains_key(name) {
+                    npm_imports.insert(name.to_owned(), specifier[range].into());
+                }
+            }
+        }
+        project.npm_imports = Some(npm_imports);
+
+        Ok(project)
+    }
+
+    /// Serialize the project into the contents of a `package.json` file, using its original BOM and line endings.
+    ///
+    /// A Deno file with comments keeps its original contents, with only the values of changed imports replaced.
+    pub fn to_json_string(&self) -> serde_json::Result<String> {
+        if let Some((contents, imports)) = &self.jsonc {
+            let mut changes: Vec<_> = import_spans(&strip_jsonc(contents))
+                .into_iter()
+                .filter_map(|(key, span)| {
+                    let to = self.values["imports"].get(&key)?;
+                    (imports.get(&key) != Some(to)).then(|| (span, to.to_string()))
+                })
+                .collect();
+            // Replacing from the end keeps the spans before it valid
+            changes.sort_by_key(|(span, _)| Reverse(span.start));
+
+            let mut data = contents.clone();
+            for (span, to) in changes {
+                data.replace_range(span, &to);
+            }
+            return Ok(data);
+        }
+
+        let mut data = serde_json::to_string_pretty(&self.values)?;
+        if self.crlf {
+            data = data.replace('\n', "\r\n");
+        }
+        if self.bom {
+            data.insert(0, BOM);
+        }
+
+        Ok(data)
+    }
+
+    /// Whether the project was read from a `deno.json` file.
+    pub fn is_deno(&self) -> bool {
+        self.npm_imports.is_some()
+    }
+
+    /// Dependencies listed under the given `package.json` field, e.g. `devDependencies`.
+    ///
+    /// The `npm:` imports of a `deno.json` file are its `dependencies`, since Deno has no dev or peer dependencies.
+    pub fn dependency_group(&self, key: &str) -> Option<&Map<String, Value>> {
+        match &self.npm_imports {
+            Some(npm_imports) => (key == "dependencies").then_some(npm_imports),
+            None => self.values[key].as_object(),
+        }
+    }
+
+    pub fn dependency_group_mut(&mut self, key: &str) -> Option<&mut Map<String, Value>> {
+        self.values.get_mut(key)?.as_object_mut()
     }
 
     pub fn dependencies(&self) -> Option<&Map<String, Value>> {
-        self.values["dependencies"].as_object()
+        self.dependency_group("dependencies")
     }
 
     pub fn dev_dependencies(&self) -> Option<&Map<String, Value>> {
-        self.values["devDependencies"].as_object()
+        self.dependency_group("devDependencies")
+    }
+
+    /// Whether the package is listed in both `dependencies` and `devDependencies`, which is usually a mistake.
+    pub fn is_duplicate(&self, name: &str) -> bool {
+        let listed = |dependencies: Option<&Map<String, Value>>| {
+            dependencies.is_some_and(|dependencies| dependencies.contains_key(name))
+        };
+
+        listed(self.dependencies()) && listed(self.dev_dependencies())
+    }
+
+    /// Problems with the manifest that don't prevent checking it, but likely aren't intended.
+    pub fn warnings(&self) -> Vec<String> {
+        // Such fields are skipped like absent ones, which would otherwise hide every dependency in them
+        let mut warnings: Vec<String> = DEPENDENCY_GROUPS
+            .iter()
+            .filter(|key| !matches!(self.values[**key], Value::Null | Value::Object(_)))
+            .map(|key| format!("{} is not an object, so its dependencies are skipped", key))
+            .collect();
+
+        // npm only reads the exact keys, so e.g. `devdependencies` is silently ignored by every tool
+        for key in self.values.as_object().into_iter().flat_map(Map::keys) {
+            let canonical_key = DEPENDENCY_GROUPS
+                .iter()
+                .find(|group| group.eq_ignore_ascii_case(key) && *group != key);
+            if let Some(canonical_key) = canonical_key {
+                warnings.push(format!(
+                    "{} is not read, did you mean {}?",
+                    key, canonical_key
+                ));
+            }
+        }
+
+        warnings.extend(self.duplicate_keys.iter().map(|path| {
+            format!(
+                "{} is listed more than once, only the last one is used",
+                path
+            )
+        }));
+
+        for name in self.dependencies().into_iter().flat_map(Map::keys) {
+            if self.is_duplicate(name) {
+                warnings.push(format!(
+                    "{} is listed in both dependencies and devDependencies",
+                    name
+                ));
+            }
+        }
+
+        warnings
+    }
+
+    /// Remove the `devDependencies` that are also listed in `dependencies`, returning their names.
+    pub fn remove_duplicate_dev_dependencies(&mut self) -> Vec<String> {
+        let duplicates: Vec<String> = self
+            .dependencies()
+            .into_iter()
+            .flat_map(Map::keys)
+            .filter(|name| self.is_duplicate(name))
+            .cloned()
+            .collect();
+
+        if let Some(dev_dependencies) = self.dependency_group_mut("devDependencies") {
+            // Unlike `remove`, `retain` keeps the order of the remaining entries
+            dev_dependencies.retain(|name, _| !duplicates.contains(name));
+        }
+
+        duplicates
+    }
+
+    /// Keys that were listed more than once in the file, see `warnings`.
+    pub fn duplicate_keys(&self) -> &[String] {
+        &self.duplicate_keys
+    }
+
+    /// Whether a package is only declared as optional, in `optionalDependencies` or as an optional peer, so that it may
+    /// legitimately be missing from the registry. A package that is also a regular or dev dependency is not optional.
+    pub fn is_optional(&self, name: &str) -> bool {
+        let is_in = |group| {
+            self.dependency_group(group)
+                .is_some_and(|dependencies| dependencies.contains_key(name))
+        };
+        let is_peer = is_in("peerDependencies");
+
+        (is_in("optionalDependencies") || is_peer)
+            && !is_in("dependencies")
+            && !is_in("devDependencies")
+            && (!is_peer || self.is_optional_peer(name))
+    }
+
+    /// Whether a peer dependency is marked as optional in `peerDependenciesMeta`, so installing it is up to the user.
+    pub fn is_optional_peer(&self, name: &str) -> bool {
+        self.values["peerDependenciesMeta"][name]["optional"] == true
+    }
+
+    /// Dependencies that are shipped inside the published package, from `bundleDependencies` or its
+    /// `bundledDependencies` alias. `true` instead of a list of names bundles every dependency.
+    pub fn is_bundled(&self, name: &str) -> bool {
+        let bundled = match &self.values["bundleDependencies"] {
+            Value::Null => &self.values["bundledDependencies"],
+            bundled => bundled,
+        };
+
+        match bundled {
+            Value::Bool(true) => self
+                .dependencies()
+                .is_some_and(|dependencies| dependencies.contains_key(name)),
+            Value::Array(names) => names.iter().any(|bundled| bundled == name),
+            _ => false,
+        }
+    }
+
+    /// Registry the package is published to, from `publishConfig.registry`.
+    pub fn publish_registry(&self) -> Option<&str> {
+        self.values["publishConfig"]["registry"].as_str()
+    }
+
+    /// Packages allowed to run lifecycle scripts when installed with Bun.
+    pub fn trusted_dependencies(&self) -> Vec<&str> {
+        self.values["trustedDependencies"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect()
     }
 
+    /// Tools pinned by the `packageManager` field, e.g. `pnpm@8.6.0+sha256.abc`, and the `volta` block.
+    pub fn pinned_tools(&self) -> Vec<PinnedTool> {
+        let package_manager = self.values["packageManager"]
+            .as_str()
+            .and_then(|package_manager| package_manager.rsplit_once('@'))
+            .map(|(name, version)| PinnedTool {
+                name: name.to_owned(),
+                // Drop the hash that Corepack may append to the version
+                version: version.split('+').next().unwrap_or_default().to_owned(),
+                field: "packageManager".to_owned(),
+            });
+        let volta = self.values["volta"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, version)| {
+                Some(PinnedTool {
+                    name: name.clone(),
+                    version: version.as_str()?.to_owned(),
+                    field: format!("volta.{}", name),
+                })
+            });
+
+        package_manager.into_iter().chain(volta).collect()
+    }
+
+    /// Versions pinned by `overrides`, with nested overrides flattened in the order of `package.json`.
+    pub fn overrides(&self) -> Vec<Override> {
+        let mut overrides = Vec::new();
+        if let Some(pins) = self.values["overrides"].as_object() {
+            flatten_overrides(pins, &mut Vec::new(), &mut overrides);
+        }
+
+        overrides
+    }
+
+    /// Versions pinned by `overrides` that can be updated on their own, in the order of `package.json`.
+    ///
+    /// A `.` key pins the package that contains it. References like `$react` are left out since they follow a
+    /// declared version, as are keys with a version selector like `foo@1` that cannot be fetched by name. The
+    /// top-level overrides of declared dependencies are left out too, since they have to stay in sync with the
+    /// declared versions, see `sync_overrides`.
+    pub fn override_pins(&self) -> Vec<OverridePin<'_>> {
+        let mut pins = Vec::new();
+        if let Some(overrides) = self.values["overrides"].as_object() {
+            collect_override_pins(overrides, None, "/overrides", &mut pins);
+        }
+
+        pins.retain(|pin| {
+            let top_level = format!("/overrides/{}", pointer_segment(pin.name));
+            let is_top_level = pin.pointer == top_level || pin.pointer == top_level + "/.";
+            !is_top_level || !self.is_declared(pin.name)
+        });
+        pins
+    }
+
+    /// Whether a package is listed in `dependencies`, `devDependencies` or `peerDependencies`.
+    fn is_declared(&self, name: &str) -> bool {
+        DEPENDENCY_GROUPS.iter().any(|group| {
+            self.dependency_group(group)
+                .is_some_and(|dependencies| dependencies.contains_key(name))
+        })
+    }
+
+    /// Pin the override at `pointer`, e.g. of an `OverridePin`, to `version`.
+    pub fn update_override(&mut self, pointer: &str, version: &str) {
+        self.set_pointers(&[pointer.to_owned()], version);
+    }
+
+    /// Pin the top-level override of a direct dependency to its updated `version`.
+    ///
+    /// npm refuses to install when the top-level override of a direct dependency doesn't match its declared
+    /// version. Overrides nested under other packages only apply to their copies of the package and are kept,
+    /// as are references like `$react` that follow the declared version by themselves.
+    pub fn sync_overrides(&mut self, name: &str, version: &str) {
+        let pointers: Vec<String> = self
+            .overrides()
+            .into_iter()
+            .filter(|pin| pin.path == [name] && !pin.version.starts_with('$'))
+            .map(|pin| pin.pointer)
+            .collect();
+
+        self.set_pointers(&pointers, version);
+    }
+
+    fn set_pointers(&mut self, pointers: &[String], version: &str) {
+        for pointer in pointers {
+            if let Some(v) = self.values.pointer_mut(pointer) {
+                *v = version.into();
+            }
+        }
+    }
+
+    /// Glob patterns of the workspace packages, from `workspaces` or the `workspaces.packages` of Yarn.
+    pub fn workspaces(&self) -> Vec<&str> {
+        let workspaces = &self.values["workspaces"];
+        let patterns = workspaces.get("packages").unwrap_or(workspaces);
+
+        patterns
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect()
+    }
+
+    /// Name of the package, empty for a `deno.json` file that isn't published.
     pub fn name(&self) -> &str {
-        self.values["name"].as_str().unwrap()
+        self.values["name"].as_str().unwrap_or_default()
     }
 
     pub fn version(&self) -> &str {
-        self.values["version"].as_str().unwrap()
-    }
-
-    pub fn update_dependency_version(
-        &mut self,
-        name: &str,
-        version: &String,
-        range_prefix: Option<char>,
-    ) {
-        // If package name contains `/`, represent it as `~1` to be in line with the JSON pointer spec:
-        // https://datatracker.ietf.org/doc/html/rfc6901#section-3
-        //
-        // JSON pointers are used to access and mutate `dependencies` and `devDependencies` in serialized `package.json`
-        let package_json_pointer = name.replace('/', "~1");
-        let latest_version = match range_prefix {
-            Some(range_symbol) => range_symbol.to_string() + version,
-            None => version.to_owned(),
+        self.values["version"].as_str().unwrap_or_default()
+    }
+
+    /// Lowest Node version that the project supports.
+    ///
+    /// Read from `engines.node`, falling back to the `.nvmrc` file in the project `directory`.
+    pub fn node_version(&self, directory: &Path) -> Option<Version> {
+        let declared = match self.values["engines"]["node"].as_str() {
+            Some(engines_node) => engines_node.to_owned(),
+            None => fs::read_to_string(directory.join(".nvmrc")).ok()?,
         };
+        let declared = declared.trim().trim_start_matches('v');
 
-        if let Some(v) = self
-            .values
-            .pointer_mut(format!("/dependencies/{}", package_json_pointer).as_str())
-        {
-            *v = latest_version.into();
-        } else if let Some(v) = self
-            .values
-            .pointer_mut(format!("/devDependencies/{}", package_json_pointer).as_str())
-        {
-            *v = latest_version.into();
+        Range::parse(declared).ok()?.min_version()
+    }
+
+    /// Update the declared version of a dependency listed under the `group` field, e.g. `devDependencies`.
+    ///
+    /// A package listed in several groups is only updated in the given one. In a `deno.json` file, every `npm:`
+    /// import of the package with the listed range is rewritten, keeping its subpath.
+    pub fn update_dependency_version(&mut self, group: &str, name: &str, version: &str) {
+        if let Some(npm_imports) = &mut self.npm_imports {
+            let declared = match npm_imports.get_mut(name) {
+                Some(declared) => std::mem::replace(declared, version.into()),
+                None => return,
+            };
+            // Aliases of the same package with another range, e.g. `npm:lodash@^3.10.0`, stay on their own version
+            let imports = self.values["imports"].as_object_mut().into_iter().flatten();
+            for (_, specifier) in imports {
+                let updated = match specifier.as_str().and_then(npm_specifier) {
+                    Some((imported, range))
+                        if imported == name
+                            && declared.as_str()
+                                == specifier.as_str().map(|s| &s[range.clone()]) =>
+                    {
+                        let specifier = specifier.as_str().unwrap_or_default();
+                        format!(
+                            "{}{}{}",
+                            &specifier[..range.start],
+                            version,
+                            &specifier[range.end..]
+                        )
+                    }
+                    _ => continue,
+                };
+                *specifier = updated.into();
+            }
+            return;
+        }
+
+        let declared = self
+            .dependency_group_mut(group)
+            .and_then(|dependencies| dependencies.get_mut(name));
+        if let Some(declared) = declared {
+            *declared = version.into();
+        }
+    }
+
+    /// Name of the file the project was read from, e.g. `package.json`.
+    pub fn file_name(&self) -> String {
+        match self.path.file_name() {
+            Some(file_name) => file_name.to_string_lossy().into_owned(),
+            None => "package.json".to_owned(),
         }
     }
 
+    /// Directory that contains the file, where `node_modules` and git commands are looked up.
+    fn directory(&self) -> &Path {
+        match self.path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        }
+    }
+
+    /// Version of a dependency installed in `node_modules`, `None` if it is not installed.
+    ///
+    /// Like Node resolves modules, the `node_modules` next to the file comes first, then the ones of its parent
+    /// directories, where workspaces hoist their packages.
+    pub fn installed_version(&self, name: &str) -> Option<Version> {
+        let directory = fs::canonicalize(self.directory()).ok()?;
+        let contents = directory
+            .ancestors()
+            .filter(|ancestor| ancestor.file_name() != Some("node_modules".as_ref()))
+            .find_map(|ancestor| {
+                let path = ancestor
+                    .join("node_modules")
+                    .join(name)
+                    .join("package.json");
+                fs::read_to_string(path).ok()
+            })?;
+        let manifest: Value = serde_json::from_str(&contents).ok()?;
+
+        manifest["version"].as_str()?.parse().ok()
+    }
+
+    /// Whether git knows of changes to the file that are not committed, so that written updates would mix with them.
+    ///
+    /// Files outside of a repository, untracked files and a missing `git` all count as having no changes.
+    pub fn has_uncommitted_changes(&self) -> bool {
+        let directory = self.directory();
+        let output = Command::new("git")
+            .args(["status", "--porcelain", "--untracked-files=no", "--"])
+            .arg(self.file_name())
+            .current_dir(directory)
+            .stderr(Stdio::null())
+            .output();
+
+        // Fails outside of a repository
+        output.is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+    }
+
     pub fn write_to_file(&self) -> std::io::Result<()> {
-        let mut file = File::create("./package.json")?;
-        let data = serde_json::to_string_pretty(&self.values)?;
+        let mut file = File::create(&self.path)?;
+        let data = self.to_json_string()?;
         file.write_all(data.as_bytes())?;
 
         Ok(())
     }
 }
+
+/// Package name of an `npm:` specifier and where its version range is, e.g. `@std/fmt` and `^1.0.0` in
+/// `npm:@std/fmt@^1.0.0/colors`. `None` for other specifiers and those without a version.
+fn npm_specifier(specifier: &str) -> Option<(&str, std::ops::Range<usize>)> {
+    let package = specifier.strip_prefix("npm:")?;
+    // Imports of a whole directory may start with a `/`, e.g. `npm:/lodash@4/`
+    let package = package.strip_prefix('/').unwrap_or(package);
+    let offset = specifier.len() - package.len();
+
+    // The `@` of a scope is part of the name
+    let (at, _) = package.char_indices().skip(1).find(|&(_, c)| c == '@')?;
+    let name = &package[..at];
+    let range_end = package[at..]
+        .find('/')
+        .map_or(package.len(), |end| at + end);
+    if name.matches('/').count() > usize::from(name.starts_with('@')) || range_end == at + 1 {
+        return None;
+    }
+
+    Some((name, offset + at + 1..offset + range_end))
+}
+
+/// Blank out the comments and trailing commas of JSONC, e.g. of a `deno.jsonc` file, so that it parses as JSON.
+///
+/// Everything else keeps its byte offset, so that parse errors still point at the right line and column, and so that
+/// the spans of `import_spans` point into `contents` too.
+fn strip_jsonc(contents: &str) -> String {
+    let mut json = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    let mut trailing_comma = None;
+    let blank = |json: &mut String, c: char| match c {
+        '\r' | '\n' | '\t' => json.push(c),
+        _ => json.push_str(&" ".repeat(c.len_utf8())),
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                json.push(c);
+                while let Some(c) = chars.next() {
+                    json.push(c);
+                    match c {
+                        '\\' => json.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                json.push(' ');
+                while let Some(c) = chars.next_if(|&c| c != '\n') {
+                    blank(&mut json, c);
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                json.push(' ');
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    blank(&mut json, c);
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                continue;
+            }
+            ',' => {
+                trailing_comma = Some(json.len());
+                json.push(c);
+                continue;
+            }
+            '}' | ']' => {
+                if let Some(comma) = trailing_comma {
+                    json.replace_range(comma..comma + 1, " ");
+                }
+                json.push(c);
+            }
+            _ if c.is_whitespace() => {
+                json.push(c);
+                continue;
+            }
+            _ => json.push(c),
+        }
+        trailing_comma = None;
+    }
+
+    json
+}
+
+/// Byte ranges of the string values of the top-level `imports` object in `json` by key, quotes included.
+///
+/// Values elsewhere, e.g. in `scopes` or `tasks`, are left out even when they contain the same specifier.
+fn import_spans(json: &str) -> Vec<(String, std::ops::Range<usize>)> {
+    let bytes = json.as_bytes();
+    let mut spans = Vec::new();
+    let mut depth = 0_usize;
+    let mut in_imports = false;
+    // Key of the value that comes next, in the object being scanned
+    let mut key: Option<String> = None;
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'"' => {
+                let start = index;
+                index += 1;
+                while index < bytes.len() && bytes[index] != b'"' {
+                    index += if bytes[index] == b'\\' { 2 } else { 1 };
+                }
+                let span = start..(index + 1).min(bytes.len());
+                let is_key = json[span.end..].trim_start().starts_with(':');
+                if is_key {
+                    key = serde_json::from_str(&json[span.clone()]).ok();
+                } else if let Some(key) = key.take().filter(|_| in_imports && depth == 2) {
+                    spans.push((key, span));
+                }
+            }
+            bracket @ (b'{' | b'[') => {
+                depth += 1;
+                if depth == 2 {
+                    in_imports = bracket == b'{' && key.as_deref() == Some("imports");
+                }
+                key = None;
+            }
+            b'}' | b']' => {
+                depth = depth.saturating_sub(1);
+                key = None;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    spans
+}
+
+/// Escape a key to be used in a JSON pointer, see https://datatracker.ietf.org/doc/html/rfc6901#section-3.
+fn pointer_segment(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Collect the pinned packages of an `overrides` object, whose `.` key pins the `parent` package.
+fn collect_override_pins<'a>(
+    overrides: &'a Map<String, Value>,
+    parent: Option<&'a String>,
+    pointer: &str,
+    pins: &mut Vec<OverridePin<'a>>,
+) {
+    // Scoped names start with `@`, any later one selects versions
+    let is_name = |key: &str| key.get(1..).is_some_and(|rest| !rest.contains('@'));
+
+    for (key, value) in overrides {
+        let pointer = format!("{}/{}", pointer, pointer_segment(key));
+        match value {
+            Value::String(version) if version.starts_with('$') => {}
+            Value::String(_) if key == "." => pins.extend(
+                parent
+                    .filter(|parent| is_name(parent))
+                    .map(|parent| OverridePin {
+                        name: parent,
+                        version: value,
+                        pointer,
+                    }),
+            ),
+            Value::String(_) if is_name(key) => pins.push(OverridePin {
+                name: key,
+                version: value,
+                pointer,
+            }),
+            Value::Object(nested) => collect_override_pins(nested, Some(key), &pointer, pins),
+            _ => {}
+        }
+    }
+}
+
+/// Collect the pins of an `overrides` object below `path`. The `.` key pins the package that contains it.
+fn flatten_overrides(
+    pins: &Map<String, Value>,
+    path: &mut Vec<String>,
+    overrides: &mut Vec<Override>,
+) {
+    let pointer = |path: &[String], key: &str| {
+        let mut pointer = String::from("/overrides");
+        for segment in path.iter().map(String::as_str).chain([key]) {
+            pointer.push('/');
+            pointer.push_str(&pointer_segment(segment));
+        }
+        pointer
+    };
+
+    for (key, value) in pins {
+        match value {
+            Value::String(version) if key == "." => overrides.push(Override {
+                path: path.clone(),
+                version: version.clone(),
+                pointer: pointer(path, key),
+            }),
+            Value::String(version) => {
+                let pointer = pointer(path, key);
+                path.push(key.clone());
+                overrides.push(Override {
+                    path: path.clone(),
+                    version: version.clone(),
+                    pointer,
+                });
+                path.pop();
+            }
+            Value::Object(nested) => {
+                path.push(key.clone());
+                flatten_overrides(nested, path, overrides);
+                path.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fields of `package.json` that list dependencies as an object of names and versions.
+const DEPENDENCY_GROUPS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// Paths of keys that are listed more than once in the same object of a JSON document, which `serde_json` drops.
+fn duplicate_keys(contents: &str) -> Vec<String> {
+    let mut duplicates = Vec::new();
+    let scan = KeyScan {
+        path: String::new(),
+        duplicates: &mut duplicates,
+    };
+    // The contents were already parsed successfully, so scanning them again cannot fail
+    let _ = scan.deserialize(&mut serde_json::Deserializer::from_str(contents));
+
+    duplicates
+}
+
+/// Walks a JSON value and records the paths of duplicate keys in its objects.
+struct KeyScan<'a> {
+    path: String,
+    duplicates: &'a mut Vec<String>,
+}
+
+impl<'de> DeserializeSeed<'de> for KeyScan<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeyScan<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut index = 0;
+        loop {
+            let element = KeyScan {
+                path: format!("{}[{}]", self.path, index),
+                duplicates: &mut *self.duplicates,
+            };
+            if seq.next_element_seed(element)?.is_none() {
+                return Ok(());
+            }
+            index += 1;
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut keys = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let path = match self.path.is_empty() {
+                true => key.clone(),
+                false => format!("{}.{}", self.path, key),
+            };
+            if !keys.insert(key) {
+                self.duplicates.push(path.clone());
+            }
+
+            let value = KeyScan {
+                path,
+                duplicates: &mut *self.duplicates,
+            };
+            map.next_value_seed(value)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn explains_unreadable_manifests() {
+        let directory =
+            std::env::temp_dir().join(format!("packrat-project-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("package.json");
+        let error = |contents: Option<&str>| {
+            match contents {
+                Some(contents) => fs::write(&path, contents).unwrap(),
+                None => fs::remove_file(&path).unwrap_or(()),
+            }
+            Project::new(&path).err().unwrap().to_string()
+        };
+
+        assert_eq!(error(None), format!("{} not found", path.display()));
+        fs::create_dir(&path).unwrap();
+        assert_eq!(
+            Project::new(&path).err().unwrap().to_string(),
+            format!(
+                "{} is a directory, expected a package.json file",
+                path.display()
+            )
+        );
+        fs::remove_dir(&path).unwrap();
+        assert_eq!(
+            error(Some("{\n  \"name\": \"demo\",\n")),
+            format!(
+                "{}:3:0: the JSON ends unexpectedly, is a closing brace or bracket missing?",
+                path.display()
+            )
+        );
+        assert_eq!(
+            error(Some("{\n  \"name\": demo\n}")),
+            format!("{}:2:11: invalid JSON, expected value", path.display())
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn detects_uncommitted_changes() {
+        let directory =
+            std::env::temp_dir().join(format!("packrat-project-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("package.json");
+        fs::write(&path, "{}").unwrap();
+        let git = |args: &[&str]| {
+            // Settings of the global config, e.g. signed commits, would make the commit fail
+            let output = Command::new("git")
+                .args([
+                    "-c",
+                    "user.name=packrat",
+                    "-c",
+                    "user.email=packrat@example.com",
+                    "-c",
+                    "commit.gpgsign=false",
+                    "-c",
+                    "core.hooksPath=/dev/null",
+                ])
+                .args(args)
+                .current_dir(&directory)
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "git {:?}: {}",
+                args,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        };
+
+        // Outside of a repository
+        assert!(!Project::new(&path).unwrap().has_uncommitted_changes());
+        git(&["init", "--quiet"]);
+        git(&["add", "package.json"]);
+        git(&["commit", "--quiet", "--message", "Add package.json"]);
+        assert!(!Project::new(&path).unwrap().has_uncommitted_changes());
+
+        fs::write(&path, r#"{ "name": "demo" }"#).unwrap();
+        assert!(Project::new(&path).unwrap().has_uncommitted_changes());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_manifests_that_are_not_objects() {
+        let directory =
+            std::env::temp_dir().join(format!("packrat-project-root-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("package.json");
+
+        for contents in [r#"[{ "name": "demo" }]"#, r#""demo""#] {
+            fs::write(&path, contents).unwrap();
+            assert_eq!(
+                Project::new(&path).err().unwrap().to_string(),
+                format!("{} root is not an object", path.display())
+            );
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn finds_packages_hoisted_to_parent_directories() {
+        let directory =
+            std::env::temp_dir().join(format!("packrat-project-hoisted-{}", std::process::id()));
+        let workspace = directory.join("packages").join("app");
+        let install = |root: &Path, name: &str, version: &str| {
+            let package = root.join("node_modules").join(name);
+            fs::create_dir_all(&package).unwrap();
+            let manifest = format!(r#"{{ "version": "{}" }}"#, version);
+            fs::write(package.join("package.json"), manifest).unwrap();
+        };
+        install(&directory, "react", "18.2.0");
+        install(&directory, "@types/node", "20.1.0");
+        install(&workspace, "react", "17.0.2");
+        let path = workspace.join("package.json");
+        fs::write(&path, r#"{ "dependencies": { "react": "^17.0.2" } }"#).unwrap();
+
+        let project = Project::new(&path).unwrap();
+        let installed = |name| project.installed_version(name).map(|v| v.to_string());
+        assert_eq!(installed("react").as_deref(), Some("17.0.2"));
+        assert_eq!(installed("@types/node").as_deref(), Some("20.1.0"));
+        assert_eq!(installed("lodash"), None);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn reads_bun_manifest() {
+        let project = Project::parse(
+            r#"{
+                "name": "bun-app",
+                "version": "1.0.0",
+                "module": "index.ts",
+                "type": "module",
+                "trustedDependencies": ["esbuild", "sharp"],
+                "dependencies": { "hono": "^3.0.0" },
+                "devDependencies": { "bun-types": "latest" },
+                "peerDependencies": { "typescript": "^5.0.0" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(project.trusted_dependencies(), ["esbuild", "sharp"]);
+        assert_eq!(project.dependencies().unwrap()["hono"], "^3.0.0");
+        assert_eq!(project.dev_dependencies().unwrap()["bun-types"], "latest");
+    }
+
+    #[test]
+    fn reads_npm_imports_of_deno_projects() {
+        let project = Project::parse_deno(
+            r#"{
+                "imports": {
+                    "chalk": "npm:chalk@^5.3.0",
+                    "@std/fmt": "npm:@std/fmt@~1.0.0",
+                    "lodash/": "npm:/lodash@4.17.0/",
+                    "lodash": "npm:lodash@4.17.0",
+                    "@std/path": "jsr:@std/path@^1.0.0",
+                    "preact": "https://esm.sh/preact@10.19.0",
+                    "left-pad": "npm:left-pad"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let dependencies: Vec<_> = project
+            .dependencies()
+            .unwrap()
+            .iter()
+            .map(|(name, declared)| (name.as_str(), declared.as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            dependencies,
+            [
+                ("chalk", "^5.3.0"),
+                ("@std/fmt", "~1.0.0"),
+                ("lodash", "4.17.0")
+            ]
+        );
+        assert_eq!(project.dev_dependencies(), None);
+        assert_eq!(project.name(), "");
+    }
+
+    #[test]
+    fn rewrites_npm_imports_of_deno_projects() {
+        let mut project = Project::parse_deno(
+            r#"{
+                "imports": {
+                    "lodash/": "npm:/lodash@^4.17.0/",
+                    "lodash": "npm:lodash@^4.17.0",
+                    "@std/fmt/colors": "npm:@std/fmt@^1.0.0/colors",
+                    "chalk": "npm:chalk@^5.3.0",
+                    "lodash3": "npm:lodash@^3.10.0"
+                }
+            }"#,
+        )
+        .unwrap();
+        project.update_dependency_version("dependencies", "lodash", "^4.17.21");
+        project.update_dependency_version("dependencies", "@std/fmt", "^1.2.0");
+
+        assert_eq!(
+            project.values["imports"],
+            serde_json::json!({
+                "lodash/": "npm:/lodash@^4.17.21/",
+                "lodash": "npm:lodash@^4.17.21",
+                "@std/fmt/colors": "npm:@std/fmt@^1.2.0/colors",
+                "chalk": "npm:chalk@^5.3.0",
+                "lodash3": "npm:lodash@^3.10.0"
+            })
+        );
+        assert_eq!(project.dependencies().unwrap()["lodash"], "^4.17.21");
+    }
+
+    #[test]
+    fn only_rewrites_the_changed_imports_of_deno_jsonc_files() {
+        let contents = r#"{
+            // Keep "npm:lodash@^4.17.0" until the migration is done — see #12
+            "imports": { "lodash": "npm:lodash@^4.17.0", "chalk": "npm:chalk@^5.3.0" },
+            "scopes": { "./legacy/": { "lodash": "npm:lodash@^4.17.0" } },
+            "tasks": { "lodash": "echo npm:lodash@^4.17.0" },
+        }"#;
+        let mut project = Project::parse_deno(contents).unwrap();
+
+        project.update_dependency_version("dependencies", "lodash", "^4.17.21");
+        let written = project.to_json_string().unwrap();
+        assert_eq!(
+            written,
+            contents.replacen(
+                r#""lodash": "npm:lodash@^4.17.0", "chalk""#,
+                r#""lodash": "npm:lodash@^4.17.21", "chalk""#,
+                1
+            )
+        );
+        assert_eq!(written.matches("lodash@^4.17.0").count(), 3);
+    }
+
+    #[test]
+    fn keeps_the_comments_of_deno_jsonc_files() {
+        let contents = r#"{
+            // Packages from npm
+            "imports": {
+                "lodash": "npm:lodash@^4.17.0", /* pinned, see #12 */
+                "chalk": "npm:chalk@^5.3.0",
+            },
+            "tasks": { "url": "echo https://example.com//*" },
+        }"#;
+        let mut project = Project::parse_deno(contents).unwrap();
+        assert_eq!(project.dependencies().unwrap()["lodash"], "^4.17.0");
+        assert_eq!(
+            project.values["tasks"]["url"],
+            "echo https://example.com//*"
+        );
+
+        project.update_dependency_version("dependencies", "lodash", "^4.17.21");
+        assert_eq!(
+            project.to_json_string().unwrap(),
+            contents.replace("lodash@^4.17.0", "lodash@^4.17.21")
+        );
+    }
+
+    #[test]
+    fn reads_pinned_tools() {
+        let project = Project::parse(
+            r#"{
+                "name": "pinned",
+                "version": "1.0.0",
+                "packageManager": "pnpm@8.6.0+sha256.0123abcd",
+                "volta": { "node": "18.16.0", "yarn": "1.22.19" }
+            }"#,
+        )
+        .unwrap();
+
+        let tool = |name: &str, version: &str, field: &str| PinnedTool {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            field: field.to_owned(),
+        };
+        assert_eq!(
+            project.pinned_tools(),
+            [
+                tool("pnpm", "8.6.0", "packageManager"),
+                tool("node", "18.16.0", "volta.node"),
+                tool("yarn", "1.22.19", "volta.yarn"),
+            ]
+        );
+    }
+
+    fn project_with_overrides() -> Project {
+        Project::parse(
+            r#"{
+                "name": "overrides",
+                "version": "1.0.0",
+                "dependencies": { "foo": "^1.0.0", "bar": "^2.0.0", "react": "^17.0.2" },
+                "overrides": {
+                    "foo": "1.0.0",
+                    "bar": {
+                        ".": "2.0.0",
+                        "baz": { "foo": "1.0.0" },
+                        "@scope/qux": "3.0.0"
+                    },
+                    "react": "$react"
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn flattens_nested_overrides() {
+        let overrides: Vec<_> = project_with_overrides()
+            .overrides()
+            .into_iter()
+            .map(|pin| (pin.path.join(" > "), pin.version, pin.pointer))
+            .collect();
+
+        let pin = |path: &str, version: &str, pointer: &str| {
+            (path.to_owned(), version.to_owned(), pointer.to_owned())
+        };
+        assert_eq!(
+            overrides,
+            [
+                pin("foo", "1.0.0", "/overrides/foo"),
+                pin("bar", "2.0.0", "/overrides/bar/."),
+                pin("bar > baz > foo", "1.0.0", "/overrides/bar/baz/foo"),
+                pin("bar > @scope/qux", "3.0.0", "/overrides/bar/@scope~1qux"),
+                pin("react", "$react", "/overrides/react"),
+            ]
+        );
+    }
+
+    #[test]
+    fn syncs_top_level_overrides_only() {
+        let mut project = project_with_overrides();
+        project.sync_overrides("foo", "^1.2.0");
+        project.sync_overrides("bar", "^2.1.0");
+        project.sync_overrides("react", "^18.2.0");
+
+        let overrides = &project.values["overrides"];
+        assert_eq!(overrides["foo"], "^1.2.0");
+        assert_eq!(overrides["bar"]["."], "^2.1.0");
+        assert_eq!(overrides["bar"]["baz"]["foo"], "1.0.0");
+        assert_eq!(overrides["react"], "$react");
+    }
+
+    #[test]
+    fn updates_override_pins_at_any_depth() {
+        let mut project = project_with_overrides();
+        let pins: Vec<_> = project
+            .override_pins()
+            .into_iter()
+            .map(|pin| (pin.name.to_owned(), pin.version.to_string(), pin.pointer))
+            .collect();
+        let pin = |name: &str, version: &str, pointer: &str| {
+            (
+                name.to_owned(),
+                format!("{:?}", version),
+                pointer.to_owned(),
+            )
+        };
+        // The top-level pins of the declared foo and bar follow their declared versions instead
+        assert_eq!(
+            pins,
+            [
+                pin("foo", "1.0.0", "/overrides/bar/baz/foo"),
+                pin("@scope/qux", "3.0.0", "/overrides/bar/@scope~1qux"),
+            ]
+        );
+
+        project.update_override("/overrides/bar/baz/foo", "1.2.0");
+        project.update_override("/overrides/bar/@scope~1qux", "3.1.0");
+
+        let overrides = &project.values["overrides"];
+        assert_eq!(overrides["foo"], "1.0.0");
+        assert_eq!(overrides["bar"]["baz"]["foo"], "1.2.0");
+        assert_eq!(overrides["bar"]["@scope/qux"], "3.1.0");
+        assert_eq!(overrides["bar"]["."], "2.0.0");
+    }
+
+    #[test]
+    fn reads_bundled_dependencies_in_both_spellings() {
+        for key in ["bundleDependencies", "bundledDependencies"] {
+            let project = Project::parse(&format!(
+                r#"{{ "dependencies": {{ "react": "^18.2.0", "lodash": "^4.17.21" }}, "{}": ["lodash"] }}"#,
+                key
+            ))
+            .unwrap();
+            assert!(project.is_bundled("lodash"), "{}", key);
+            assert!(!project.is_bundled("react"), "{}", key);
+        }
+
+        let project = Project::parse(
+            r#"{ "dependencies": { "react": "^18.2.0" }, "bundleDependencies": true }"#,
+        )
+        .unwrap();
+        assert!(project.is_bundled("react"));
+        assert!(!project.is_bundled("left-pad"));
+    }
+
+    #[test]
+    fn warns_about_duplicates() {
+        let project = Project::parse(
+            r#"{
+                "dependencies": { "react": "^17.0.0", "lodash": "^4.17.0", "react": "^18.2.0" },
+                "devDependencies": { "lodash": "^4.17.21", "eslint": "^8.0.0" },
+                "files": [{ "a": 1, "a": 2 }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(project.values["dependencies"]["react"], "^18.2.0");
+        assert_eq!(
+            project.warnings(),
+            [
+                "dependencies.react is listed more than once, only the last one is used",
+                "files[0].a is listed more than once, only the last one is used",
+                "lodash is listed in both dependencies and devDependencies",
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_about_dependency_groups_that_are_not_objects() {
+        for dev_dependencies in ["[]", r#"["eslint"]"#, r#""eslint""#] {
+            let project = Project::parse(&format!(
+                r#"{{ "dependencies": {{ "react": "^17.0.2" }}, "devDependencies": {} }}"#,
+                dev_dependencies
+            ))
+            .unwrap();
+
+            assert_eq!(project.dev_dependencies(), None);
+            assert_eq!(
+                project.warnings(),
+                ["devDependencies is not an object, so its dependencies are skipped"]
+            );
+        }
+
+        let project = Project::parse(r#"{ "devDependencies": null }"#).unwrap();
+        assert!(project.warnings().is_empty());
+    }
+
+    #[test]
+    fn writes_dependency_groups_under_their_exact_keys() {
+        let mut project = Project::parse(
+            r#"{
+                "name": "legacy",
+                "version": "1.0.0",
+                "devDependencies": { "eslint": "^8.0.0" },
+                "devdependencies": { "mocha": "^9.0.0" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            project.warnings(),
+            ["devdependencies is not read, did you mean devDependencies?"]
+        );
+
+        project.update_dependency_version("devDependencies", "eslint", "^9.0.0");
+        // Packages of the misspelled group are not listed, so they are never updated
+        project.update_dependency_version("devDependencies", "mocha", "^10.0.0");
+        project.update_dependency_version("dependencies", "eslint", "^9.0.0");
+
+        let written = Project::parse(&project.to_json_string().unwrap()).unwrap();
+        let keys: Vec<&String> = written.values.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            ["name", "version", "devDependencies", "devdependencies"]
+        );
+        assert_eq!(
+            written.values["devDependencies"],
+            json!({ "eslint": "^9.0.0" })
+        );
+        assert_eq!(
+            written.values["devdependencies"],
+            json!({ "mocha": "^9.0.0" })
+        );
+    }
+
+    #[test]
+    fn removes_dev_dependencies_listed_as_dependencies() {
+        let mut project = project();
+
+        assert_eq!(project.remove_duplicate_dev_dependencies(), ["shared"]);
+        assert_eq!(project.values["dependencies"]["shared"], "^1.0.0");
+        assert!(project.values["devDependencies"].get("shared").is_none());
+        assert_eq!(project.values["devDependencies"]["eslint"], "8.0.0");
+        assert!(project.warnings().is_empty());
+    }
+
+    #[test]
+    fn reads_workspace_patterns() {
+        let npm = Project::parse(r#"{ "workspaces": ["packages/*", "apps/web"] }"#).unwrap();
+        assert_eq!(npm.workspaces(), ["packages/*", "apps/web"]);
+
+        let yarn = Project::parse(
+            r#"{ "workspaces": { "packages": ["packages/*"], "nohoist": ["**/react"] } }"#,
+        )
+        .unwrap();
+        assert_eq!(yarn.workspaces(), ["packages/*"]);
+
+        assert!(project().workspaces().is_empty());
+    }
+
+    #[test]
+    fn reads_optional_peers() {
+        let project = Project::parse(
+            r#"{
+                "peerDependencies": { "react": "^18.0.0", "react-native": "*", "typescript": "^5.0.0" },
+                "peerDependenciesMeta": { "react-native": { "optional": true }, "typescript": { "optional": false } }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(project.is_optional_peer("react-native"));
+        assert!(!project.is_optional_peer("typescript"));
+        assert!(!project.is_optional_peer("react"));
+        assert_eq!(
+            project.dependency_group("peerDependencies").unwrap()["react"],
+            "^18.0.0"
+        );
+    }
+
+    #[test]
+    fn only_counts_packages_declared_solely_as_optional() {
+        let project = Project::parse(
+            r#"{
+                "dependencies": { "sharp": "^0.33.0" },
+                "devDependencies": { "react": "^18.2.0" },
+                "peerDependencies": { "react": "^18.0.0", "react-native": "*", "typescript": "^5.0.0" },
+                "peerDependenciesMeta": { "react": { "optional": true }, "react-native": { "optional": true } },
+                "optionalDependencies": { "fsevents": "^2.3.0", "sharp": "^0.33.0" }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(project.is_optional("fsevents"));
+        assert!(project.is_optional("react-native"));
+        // Also installed as a dev dependency, so a failure to fetch it is real
+        assert!(!project.is_optional("react"));
+        assert!(!project.is_optional("sharp"));
+        assert!(!project.is_optional("typescript"));
+        assert!(!project.is_optional("left-pad"));
+    }
+
+    fn project() -> Project {
+        Project::parse(
+            r#"{
+                "name": "packrat",
+                "version": "1.0.0",
+                "dependencies": {
+                    "react": "^17.0.2",
+                    "@scope/pkg": "~1.0.0",
+                    "shared": "^1.0.0"
+                },
+                "devDependencies": {
+                    "eslint": "8.0.0",
+                    "shared": "^1.0.0"
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn updates_runtime_dependency() {
+        let mut project = project();
+        project.update_dependency_version("dependencies", "react", "^18.2.0");
+
+        assert_eq!(project.values["dependencies"]["react"], "^18.2.0");
+    }
+
+    #[test]
+    fn updates_dev_dependency() {
+        let mut project = project();
+        project.update_dependency_version("devDependencies", "eslint", "8.57.0");
+
+        assert_eq!(project.values["devDependencies"]["eslint"], "8.57.0");
+    }
+
+    #[test]
+    fn updates_scoped_dependency() {
+        let mut project = project();
+        project.update_dependency_version("dependencies", "@scope/pkg", "~1.2.0");
+
+        assert_eq!(project.values["dependencies"]["@scope/pkg"], "~1.2.0");
+    }
+
+    #[test]
+    fn updates_dependencies_with_pointer_characters_in_name() {
+        let mut project = Project::parse(
+            r#"{
+                "dependencies": { "a~1b": "^1.0.0", "a/b": "^1.0.0", "@scope/tilde~": "^1.0.0", "~": "^1.0.0" }
+            }"#,
+        )
+        .unwrap();
+
+        for name in ["a~1b", "@scope/tilde~", "~"] {
+            project.update_dependency_version("dependencies", name, "^2.0.0");
+        }
+
+        let dependencies = &project.values["dependencies"];
+        assert_eq!(dependencies["a~1b"], "^2.0.0");
+        assert_eq!(dependencies["a/b"], "^1.0.0");
+        assert_eq!(dependencies["@scope/tilde~"], "^2.0.0");
+        assert_eq!(dependencies["~"], "^2.0.0");
+    }
+
+    #[test]
+    fn ignores_missing_dependency_group() {
+        let mut project = Project::parse(r#"{ "dependencies": "invalid" }"#).unwrap();
+        project.update_dependency_version("devDependencies", "react", "18.2.0");
+        project.update_dependency_version("dependencies", "react", "18.2.0");
+
+        assert_eq!(project.values["dependencies"], "invalid");
+        assert!(project.values.get("devDependencies").is_none());
+    }
+
+    #[test]
+    fn updates_dependency_in_both_groups_only_in_given_group() {
+        let mut project = project();
+        assert!(project.is_duplicate("shared"));
+        assert!(!project.is_duplicate("react"));
+
+        project.update_dependency_version("devDependencies", "shared", "^2.0.0");
+        assert_eq!(project.values["dependencies"]["shared"], "^1.0.0");
+        assert_eq!(project.values["devDependencies"]["shared"], "^2.0.0");
+
+        project.update_dependency_version("dependencies", "shared", "^2.0.0");
+        assert_eq!(project.values["dependencies"]["shared"], "^2.0.0");
+    }
+
+    #[test]
+    fn ignores_unknown_dependency() {
+        let mut project = project();
+        let before = project.values.clone();
+        project.update_dependency_version("dependencies", "unknown", "1.0.0");
+
+        assert_eq!(project.values, before);
+    }
+
+    #[test]
+    fn round_trips_bom() {
+        let contents = "\u{feff}{\n  \"name\": \"packrat\"\n}";
+        let project = Project::parse(contents).unwrap();
+
+        assert_eq!(project.name(), "packrat");
+        assert_eq!(project.to_json_string().unwrap(), contents);
+    }
+
+    #[test]
+    fn round_trips_crlf() {
+        let contents = "{\r\n  \"name\": \"packrat\",\r\n  \"dependencies\": {\r\n    \"react\": \"^18.2.0\"\r\n  }\r\n}";
+        let project = Project::parse(contents).unwrap();
+
+        assert_eq!(project.to_json_string().unwrap(), contents);
+    }
+
+    #[test]
+    fn keeps_lf_line_endings() {
+        let contents = "{\n  \"name\": \"packrat\"\n}";
+        let project = Project::parse(contents).unwrap();
+
+        assert_eq!(project.to_json_string().unwrap(), contents);
+    }
+}
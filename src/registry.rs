@@ -2,37 +2,611 @@
 //!
 //! For reference, see [official NPM registry documentation](https://github.com/npm/registry/blob/master/docs/responses/package-metadata.md).
 
-use reqwest::{header::ACCEPT, Client};
-use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    error::Error as _,
+    fmt, io,
+    time::{Duration, SystemTime},
+};
+
+use futures::{stream, Stream, StreamExt};
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE},
+    redirect, Certificate, Client, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::{self, Instant};
 
 /// Registry metadata of an NPM package.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub struct Metadata {
     pub name: String,
-    pub dist_tags: LatestVersion,
+    pub dist_tags: DistTags,
+    /// Every published version of the package, keyed by version.
+    #[serde(default)]
+    pub versions: HashMap<String, VersionMetadata>,
+    /// When each version was published, keyed by version. Only full metadata lists it, e.g. from some mirrors.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub time: HashMap<String, String>,
+    /// When the package last changed, which is usually when its latest version was published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+}
+
+impl Metadata {
+    /// When the latest version was published, approximated by the last change if the registry doesn't list it.
+    pub fn latest_published(&self) -> Option<SystemTime> {
+        let timestamp = self
+            .time
+            .get(&self.dist_tags.latest)
+            .or(self.modified.as_ref())?;
+
+        humantime::parse_rfc3339_weak(timestamp).ok()
+    }
+
+    /// Version tagged as latest if it's missing from the listed versions, e.g. while a mirror catches up.
+    pub fn unlisted_latest(&self) -> Option<&str> {
+        let latest = &self.dist_tags.latest;
+        match self.versions.is_empty() || self.versions.contains_key(latest) {
+            true => None,
+            false => Some(latest),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
-pub struct LatestVersion {
+/// Versions that the package tags, e.g. `latest` or `next`.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DistTags {
     pub latest: String,
+    #[serde(flatten)]
+    pub other: HashMap<String, String>,
+}
+
+impl DistTags {
+    /// Version tagged with `tag`, if the package has such a tag.
+    pub fn get(&self, tag: &str) -> Option<&str> {
+        match tag {
+            "latest" => Some(&self.latest),
+            tag => self.other.get(tag).map(String::as_str),
+        }
+    }
+}
+
+/// Registry metadata of a single published version.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct VersionMetadata {
+    /// Runtimes that the version supports. Old packages may use an array instead of an object.
+    #[serde(default)]
+    pub engines: Value,
+}
+
+impl VersionMetadata {
+    /// Range of Node versions that this version declares support for.
+    pub fn node_engine(&self) -> Option<&str> {
+        self.engines.get("node")?.as_str()
+    }
+}
+
+/// Reasons why the metadata of a package could not be fetched.
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    /// The response was an HTML page, usually from a captive portal or a proxy rather than the registry itself.
+    Html,
+    /// The registry answered successfully but without a body, which is usually a transient glitch.
+    Empty,
+    Json(serde_json::Error),
+    /// The registry doesn't have the package.
+    NotFound,
+    /// The registry answered with an error status other than 404, e.g. 401 from a private registry without a token.
+    Status(StatusCode),
+    /// The `--timeout-total` budget ran out before the package was fetched.
+    TimedOut,
+}
+
+/// Common reasons why the registry could not be reached, which are explained instead of showing the raw error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionProblem {
+    Dns,
+    Refused,
+    Tls,
+    Timeout,
+}
+
+impl ConnectionProblem {
+    fn of(error: &reqwest::Error) -> Option<Self> {
+        if error.is_timeout() {
+            return Some(ConnectionProblem::Timeout);
+        }
+
+        // reqwest doesn't categorize connection errors, so look for the underlying cause
+        let mut source = error.source();
+        while let Some(cause) = source {
+            if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+                match io_error.kind() {
+                    io::ErrorKind::ConnectionRefused => return Some(ConnectionProblem::Refused),
+                    io::ErrorKind::TimedOut => return Some(ConnectionProblem::Timeout),
+                    _ => {}
+                }
+            }
+            let message = cause.to_string().to_lowercase();
+            if message.contains("dns error") || message.contains("failed to lookup address") {
+                return Some(ConnectionProblem::Dns);
+            }
+            if ["certificate", "tls", "ssl"]
+                .iter()
+                .any(|keyword| message.contains(keyword))
+            {
+                return Some(ConnectionProblem::Tls);
+            }
+            source = cause.source();
+        }
+
+        None
+    }
+
+    fn describe(self, f: &mut fmt::Formatter<'_>, host: &str) -> fmt::Result {
+        match self {
+            ConnectionProblem::Dns => {
+                write!(f, "DNS resolution failed for {} — check your network", host)
+            }
+            ConnectionProblem::Refused => write!(
+                f,
+                "connection to {} refused — check the registry URL and port",
+                host
+            ),
+            ConnectionProblem::Tls => write!(
+                f,
+                "TLS handshake with {} failed — check its certificate or your proxy, or trust its CA with --ca-cert",
+                host
+            ),
+            ConnectionProblem::Timeout => write!(
+                f,
+                "request to {} timed out — the network or the registry is slow",
+                host
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Request(e) => match ConnectionProblem::of(e) {
+                Some(problem) => {
+                    let host = e
+                        .url()
+                        .and_then(|url| url.host_str())
+                        .unwrap_or("the registry");
+                    problem.describe(f, host)
+                }
+                None => write!(f, "{}", e),
+            },
+            Error::Html => f.write_str("registry returned non-JSON (captive portal or proxy?)"),
+            Error::Empty => f.write_str("empty registry response"),
+            Error::Json(e) => write!(f, "invalid package metadata: {}", e),
+            Error::NotFound => f.write_str("not found in the registry"),
+            Error::Status(status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)) => write!(
+                f,
+                "registry answered {} — check the credentials for the registry",
+                status
+            ),
+            Error::Status(status) => write!(f, "registry answered {}", status),
+            Error::TimedOut => f.write_str("timed out"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// Whether fetching the package again is likely to succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::Empty)
+    }
+
+    /// Whether the registry itself could not be reached properly, so that every package likely fails the same way.
+    pub fn is_registry_wide(&self) -> bool {
+        match self {
+            Error::Request(e) => ConnectionProblem::of(e).is_some(),
+            Error::Html => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Request(e)
+    }
 }
 
 /// NPM registry base URL.
-const REGISTRY_URL: &str = "https://registry.npmjs.org/";
+pub const REGISTRY_URL: &str = "https://registry.npmjs.org/";
+/// `User-Agent` header that identifies packrat to registries and proxies.
+pub const USER_AGENT: &str = concat!("packrat/", env!("CARGO_PKG_VERSION"));
 /// `ACCEPT` header that signals to registry to respond with metadata in abbreviated form.
 const ACCEPT_ABBREVIATED: &str =
     "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*";
 
+/// Maximum number of redirects to follow, e.g. from a registry mirror to its storage.
+const MAX_REDIRECTS: usize = 10;
+/// Number of times a package is fetched again after a retryable error.
+const RETRIES: usize = 2;
+
+/// Shown whenever TLS certificates are not verified, so that `--insecure` doesn't linger unnoticed.
+pub const INSECURE_WARNING: &str =
+    "TLS certificates of registries are not verified (--insecure), anyone on the network can impersonate them";
+
+/// How the TLS certificates of registries are verified, see `--ca-cert` and `--insecure`.
+#[derive(Clone, Default)]
+pub struct Tls {
+    /// Root certificate trusted in addition to the system ones, e.g. of a corporate CA.
+    pub ca_cert: Option<Certificate>,
+    /// Whether certificates are accepted without verification.
+    pub insecure: bool,
+}
+
+/// How connections to registries are kept open for reuse, see `--no-keepalive` and `--pool-idle-timeout`.
+#[derive(Clone, Copy, Default)]
+pub struct Pool {
+    /// Whether every request opens a connection of its own instead of reusing an idle one.
+    pub no_keepalive: bool,
+    /// How long idle connections are kept open, the default of reqwest if `None`.
+    pub idle_timeout: Option<Duration>,
+}
+
+/// HTTP client for registry requests.
+pub fn client(user_agent: &str, tls: &Tls, pool: Pool) -> Result<Client, reqwest::Error> {
+    let mut builder = Client::builder()
+        .user_agent(user_agent)
+        .redirect(redirect::Policy::limited(MAX_REDIRECTS))
+        .danger_accept_invalid_certs(tls.insecure);
+    if let Some(ca_cert) = &tls.ca_cert {
+        builder = builder.add_root_certificate(ca_cert.clone());
+    }
+    if pool.no_keepalive {
+        builder = builder.pool_max_idle_per_host(0);
+    }
+    if let Some(idle_timeout) = pool.idle_timeout {
+        builder = builder.pool_idle_timeout(idle_timeout);
+    }
+
+    builder.build()
+}
+
+/// Prints the connection events of hyper's pool, which reach the `log` crate through `tracing`.
+struct ConnectionLogger;
+
+impl ConnectionLogger {
+    /// Modules of hyper that tell when connections are opened, reused and put back into the pool.
+    const TARGETS: [&'static str; 2] = ["hyper::client::pool", "hyper::client::connect::http"];
+}
+
+impl log::Log for ConnectionLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Debug && Self::TARGETS.contains(&metadata.target())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("⇄ {}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Print when connections to registries are opened and reused from now on, see `--log-connections`.
+pub fn log_connections() {
+    if log::set_logger(&ConnectionLogger).is_ok() {
+        log::set_max_level(log::LevelFilter::Debug);
+    }
+}
+
+/// Registry to fetch each package from, by its scope.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registries {
+    /// Registry of packages whose scope has no registry of its own.
+    pub default: String,
+    /// Registry of each scope, e.g. `@myorg`.
+    pub scopes: HashMap<String, String>,
+}
+
+impl Registries {
+    /// Base URL of the registry of a package.
+    pub fn url(&self, package_name: &str) -> &str {
+        package_name
+            .split_once('/')
+            .filter(|(scope, _)| scope.starts_with('@'))
+            .and_then(|(scope, _)| self.scopes.get(scope))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// URL of the metadata of a package, relative to the registry base URL.
+///
+/// The base URL may contain a path, e.g. `https://npm.example.com/repository/npm-group/`, with or without a
+/// trailing slash. The slash of scoped package names is encoded, like npm does.
+fn package_url(registry_url: &str, package_name: &str) -> String {
+    format!(
+        "{}/{}",
+        registry_url.trim_end_matches('/'),
+        package_name.replace('/', "%2f")
+    )
+}
+
 pub async fn fetch_metadata(
     client: &Client,
+    registries: &Registries,
     package_name: &str,
-) -> Result<Metadata, reqwest::Error> {
-    client
-        .get(REGISTRY_URL.to_owned() + package_name)
+) -> Result<Metadata, Error> {
+    let response = client
+        .get(package_url(registries.url(package_name), package_name))
         .header(ACCEPT, ACCEPT_ABBREVIATED)
         .send()
-        .await?
-        .json::<Metadata>()
+        .await?;
+    match response.status() {
+        StatusCode::NOT_FOUND => return Err(Error::NotFound),
+        status if !status.is_success() => return Err(Error::Status(status)),
+        _ => {}
+    }
+    // Only a successful response is expected to hold metadata, so only its body tells about proxies and glitches
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    let body = response.bytes().await?;
+
+    if is_html || body.trim_ascii_start().starts_with(b"<") {
+        return Err(Error::Html);
+    }
+    if body.trim_ascii().is_empty() {
+        return Err(Error::Empty);
+    }
+
+    serde_json::from_slice(&body).map_err(Error::Json)
+}
+
+/// `repository` field of the latest version of a package, which the abbreviated metadata leaves out.
+pub async fn fetch_repository(
+    client: &Client,
+    registries: &Registries,
+    package_name: &str,
+) -> Result<Value, Error> {
+    let url = format!(
+        "{}/latest",
+        package_url(registries.url(package_name), package_name)
+    );
+    let manifest: Value = client.get(url).send().await?.json().await?;
+
+    Ok(manifest["repository"].clone())
+}
+
+/// Like `fetch_metadata`, but fetches the package again up to `RETRIES` times after a retryable error.
+pub async fn fetch_with_retries(
+    client: &Client,
+    registries: &Registries,
+    package_name: &str,
+) -> Result<Metadata, Error> {
+    let mut metadata = fetch_metadata(client, registries, package_name).await;
+    for _ in 0..RETRIES {
+        match &metadata {
+            Err(e) if e.is_retryable() => {
+                metadata = fetch_metadata(client, registries, package_name).await
+            }
+            _ => break,
+        }
+    }
+
+    metadata
+}
+
+/// Limits on fetching many packages at once, see `--timeout-total` and `--batch-size`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FetchLimits {
+    /// When to give up on the packages that are not fetched yet.
+    pub deadline: Option<Instant>,
+    /// Number of packages fetched together and the pause after each batch, instead of a steady stream of requests.
+    pub batches: Option<(usize, Duration)>,
+}
+
+/// Fetch metadata of several packages concurrently, yielding each result with its package name as it arrives.
+///
+/// Packages that are not fetched by the deadline yield `Error::TimedOut`, so the stream ends soon after it.
+pub fn fetch_all<'a>(
+    client: &'a Client,
+    registries: &'a Registries,
+    package_names: impl IntoIterator<Item = &'a String> + 'a,
+    limits: FetchLimits,
+) -> impl Stream<Item = (&'a String, Result<Metadata, Error>)> + 'a {
+    let fetch = move |package_name: &'a String| async move {
+        let fetch = fetch_with_retries(client, registries, package_name);
+        let metadata = match limits.deadline {
+            Some(deadline) => time::timeout_at(deadline, fetch)
+                .await
+                .unwrap_or(Err(Error::TimedOut)),
+            None => fetch.await,
+        };
+        (package_name, metadata)
+    };
+
+    match limits.batches {
+        None => stream::iter(package_names)
+            .map(fetch)
+            .buffer_unordered(10)
+            .left_stream(),
+        Some((size, delay)) => stream::iter(package_names)
+            .chunks(size)
+            .enumerate()
+            .flat_map(move |(index, batch)| {
+                let paused_batch = async move {
+                    if index != 0 {
+                        // Waiting past the deadline would only delay the timeout of the remaining packages
+                        let resume = Instant::now() + delay;
+                        time::sleep_until(limits.deadline.map_or(resume, |d| d.min(resume))).await;
+                    }
+                    batch
+                };
+                stream::once(Box::pin(paused_batch))
+                    .flat_map(move |batch| stream::iter(batch).map(fetch).buffer_unordered(size))
+            })
+            .right_stream(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_registry_url_with_path_prefix() {
+        for registry_url in [
+            "https://npm.example.com/repository/npm-group/",
+            "https://npm.example.com/repository/npm-group",
+        ] {
+            assert_eq!(
+                package_url(registry_url, "react"),
+                "https://npm.example.com/repository/npm-group/react"
+            );
+            assert_eq!(
+                package_url(registry_url, "@types/node"),
+                "https://npm.example.com/repository/npm-group/@types%2fnode"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn explains_refused_connections() {
+        // Nothing listens on port 1, so connecting is refused right away
+        let registries = Registries {
+            default: "http://127.0.0.1:1/".to_owned(),
+            scopes: HashMap::new(),
+        };
+        let error = fetch_metadata(
+            &client(USER_AGENT, &Tls::default(), Pool::default()).unwrap(),
+            &registries,
+            "react",
+        )
         .await
+        .unwrap_err();
+
+        assert!(error.is_registry_wide());
+        assert_eq!(
+            error.to_string(),
+            "connection to 127.0.0.1 refused — check the registry URL and port"
+        );
+    }
+
+    #[tokio::test]
+    async fn pauses_between_batches() {
+        let registries = Registries {
+            default: "http://127.0.0.1:1/".to_owned(),
+            scopes: HashMap::new(),
+        };
+        let names = ["react", "lodash", "left-pad"].map(String::from);
+        let limits = FetchLimits {
+            deadline: None,
+            batches: Some((2, Duration::from_millis(200))),
+        };
+
+        let started = Instant::now();
+        let client = client(USER_AGENT, &Tls::default(), Pool::default()).unwrap();
+        let fetched: Vec<_> = fetch_all(&client, &registries, &names, limits)
+            .collect()
+            .await;
+
+        assert_eq!(fetched.len(), 3);
+        assert_eq!(fetched[2].0, "left-pad");
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    /// Fetch a package from a registry that answers the one request with `status`, `content_type` and `body`.
+    async fn fetch_answered(status: &str, content_type: &str, body: &str) -> Error {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let registries = Registries {
+            default: format!("http://{}/", listener.local_addr().unwrap()),
+            scopes: HashMap::new(),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        );
+        std::thread::spawn(move || {
+            use std::io::{Read, Write};
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        fetch_metadata(
+            &client(USER_AGENT, &Tls::default(), Pool::default()).unwrap(),
+            &registries,
+            "left-pad-optional",
+        )
+        .await
+        .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn tells_missing_packages_apart() {
+        // A registry that has no packages at all
+        let error = fetch_answered(
+            "404 Not Found",
+            "application/json",
+            r#"{"error":"Not found"}"#,
+        )
+        .await;
+
+        assert!(matches!(error, Error::NotFound));
+        assert!(!error.is_registry_wide());
+    }
+
+    #[tokio::test]
+    async fn reports_error_statuses_before_looking_at_the_body() {
+        let error = fetch_answered(
+            "401 Unauthorized",
+            "application/json",
+            r#"{"error":"login first"}"#,
+        )
+        .await;
+        assert!(matches!(error, Error::Status(StatusCode::UNAUTHORIZED)));
+        assert_eq!(
+            error.to_string(),
+            "registry answered 401 Unauthorized — check the credentials for the registry"
+        );
+
+        // Error pages of proxies are neither a captive portal nor a glitch worth retrying
+        let error =
+            fetch_answered("502 Bad Gateway", "text/html", "<html>Bad Gateway</html>").await;
+        assert_eq!(error.to_string(), "registry answered 502 Bad Gateway");
+        assert!(!error.is_registry_wide());
+        let error = fetch_answered("503 Service Unavailable", "application/json", "").await;
+        assert!(matches!(
+            error,
+            Error::Status(StatusCode::SERVICE_UNAVAILABLE)
+        ));
+        assert!(!error.is_retryable());
+
+        let error =
+            fetch_answered("200 OK", "text/html", "<html>Sign in to the Wi-Fi</html>").await;
+        assert!(matches!(error, Error::Html));
+        let error = fetch_answered("200 OK", "application/json", "").await;
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn joins_registry_url_without_path() {
+        assert_eq!(
+            package_url(REGISTRY_URL, "@types/node"),
+            "https://registry.npmjs.org/@types%2fnode"
+        );
+    }
 }
@@ -0,0 +1,616 @@
+//! Print outdated dependencies without starting the interactive UI, e.g. for scripts and CI.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    io::stdout,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Error};
+use crossterm::{style::Stylize, tty::IsTty};
+use futures::StreamExt;
+use reqwest::Client;
+
+use crate::{
+    application::SortMode,
+    args::{Args, ReportFormat},
+    cache::DiskCache,
+    dependency::{Dependency, FetchedPackage, Selection},
+    project::Project,
+    registry::{self, FetchLimits, Metadata, Registries},
+    text,
+    version::{self, UpdatePolicy},
+};
+
+/// Print the report and exit with a failure code if any of the checked dependencies can be updated, or with `--strict`
+/// if some of them could not be fetched.
+pub async fn run(args: Args) -> Result<ExitCode, Error> {
+    let client = args.client()?;
+    let mut cache = None;
+    // The budget of --timeout-total covers every project of the report together
+    let limits = args.fetch_limits();
+
+    let paths = match &args.projects {
+        Some(pattern) => project_paths(pattern)?,
+        None if args.workspaces => workspace_paths(&args.manifest_path()?, &args.manifest)?,
+        None => vec![args.manifest_path()?],
+    };
+
+    let mut has_updates = false;
+    let mut failed: Vec<String> = Vec::new();
+    for path in &paths {
+        let outcome = report_project(&args, path, &client, &mut cache, limits).await?;
+        has_updates |= outcome.has_updates;
+        // Projects of a monorepo share many packages, which are listed once
+        for name in outcome.failed {
+            if !failed.contains(&name) {
+                failed.push(name);
+            }
+        }
+    }
+
+    Ok(exit_code(args.strict, has_updates, &failed))
+}
+
+/// Failure if any dependency can be updated, or with `strict` if any of the `failed` packages could not be fetched.
+fn exit_code(strict: bool, has_updates: bool, failed: &[String]) -> ExitCode {
+    if strict && !failed.is_empty() {
+        eprintln!(
+            "Failing because of --strict, {} could not be fetched:",
+            match failed.len() {
+                1 => "1 package".to_owned(),
+                n => format!("{} packages", n),
+            }
+        );
+        for name in failed {
+            eprintln!("  {}", name);
+        }
+        return ExitCode::FAILURE;
+    }
+    if has_updates {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Print the packages whose latest version changed since they were last fetched, going by the disk cache.
+pub async fn diff_cache(args: Args) -> Result<ExitCode, Error> {
+    let project = Project::new(&args.manifest_path()?)?;
+    let selection = Selection::new(&args, &project)?;
+    let registries = args.registries(&project);
+    let disk_cache = match DiskCache::new(&args, registries.clone()) {
+        Some(disk_cache) => disk_cache,
+        None => bail!("No cache directory, neither XDG_CACHE_HOME nor HOME is set"),
+    };
+    // Loading drops the lines that are neither fresh nor the last of their package, so the file doesn't grow
+    disk_cache.load();
+    let snapshots = disk_cache.snapshots();
+
+    let client = args.client()?;
+    let names: Vec<&String> = selection.names(&project).collect();
+    let now = SystemTime::now();
+    let mut package_updates = registry::fetch_all(
+        &client,
+        &registries,
+        names.iter().copied(),
+        args.fetch_limits(),
+    );
+    let mut changes = Vec::new();
+    let mut is_cache_writable = true;
+    while let Some((name, package)) = package_updates.next().await {
+        let package = match package {
+            Ok(package) => package,
+            Err(e) => {
+                eprintln!("Unable to fetch {}: {}", name, e);
+                continue;
+            }
+        };
+        if is_cache_writable {
+            if let Err(e) = disk_cache.insert(&package) {
+                eprintln!("Unable to write the metadata cache: {}", e);
+                is_cache_writable = false;
+            }
+        }
+        // Packages fetched for the first time have nothing to compare with
+        if let Some((fetched_at, snapshot)) = snapshots.get(name) {
+            if snapshot.dist_tags.latest != package.dist_tags.latest {
+                let elapsed = now.duration_since(*fetched_at).unwrap_or_default();
+                changes.push((
+                    name,
+                    snapshot.dist_tags.latest.clone(),
+                    package.dist_tags.latest,
+                    elapsed,
+                ));
+            }
+        }
+    }
+
+    // Fetches complete in any order, so print in the order of package.json
+    changes.sort_by_key(|(name, ..)| names.iter().position(|n| n == name));
+    let has_changes = !changes.is_empty();
+    for (name, before, after, elapsed) in changes {
+        println!(
+            "{}: latest {} -> {} since your last check {}",
+            name,
+            before,
+            after,
+            text::ago(elapsed)
+        );
+    }
+    if has_changes {
+        Ok(ExitCode::FAILURE)
+    } else {
+        println!("No new releases since your last check");
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Manifests matched by the `--projects` glob, except the ones of installed packages.
+fn project_paths(pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+    for path in glob::glob(pattern)? {
+        let path = path?;
+        if !path.components().any(|c| c.as_os_str() == "node_modules") {
+            paths.push(path);
+        }
+    }
+
+    if paths.is_empty() {
+        bail!("No manifests match {}", pattern);
+    }
+
+    Ok(paths)
+}
+
+/// Manifests of the project at `root` and of its workspace packages, in the order of the `workspaces` patterns.
+fn workspace_paths(root: &Path, manifest: &str) -> Result<Vec<PathBuf>, Error> {
+    let project = Project::new(&root.to_owned())?;
+    let directory = root
+        .parent()
+        .expect("package.json path has a parent directory");
+
+    let mut paths = vec![root.to_owned()];
+    for pattern in project.workspaces() {
+        // Negated patterns only exclude packages, which is rare enough to not be supported
+        if pattern.starts_with('!') {
+            continue;
+        }
+        let pattern = directory.join(pattern).join(manifest);
+        for path in glob::glob(&pattern.to_string_lossy())? {
+            let path = path?;
+            if !paths.contains(&path) && !path.components().any(|c| c.as_os_str() == "node_modules")
+            {
+                paths.push(path);
+            }
+        }
+    }
+
+    if paths.len() == 1 {
+        bail!("{} does not list any workspace packages", root.display());
+    }
+
+    Ok(paths)
+}
+
+fn print_project_header(args: &Args, path: &Path, project: &Project) {
+    // Workspace packages are better known by their package names than by their directories
+    let title = match project.values["name"].as_str() {
+        Some(name) if args.workspaces => format!("{} ({})", name, path.display()),
+        _ => path.display().to_string(),
+    };
+
+    match args.report_format() {
+        ReportFormat::Markdown => println!("## `{}`\n", title),
+        // A line that isn't a row would break spreadsheet imports
+        ReportFormat::Csv => {}
+        // Every line is expected to be a package name
+        ReportFormat::Names => {}
+        // Several documents in a row are not valid JSON, but each one is
+        ReportFormat::Json => {}
+        _ if stdout().is_tty() => {
+            let title = glyphs(args, &format!("📦 {}", title)).into_owned();
+            println!("{}\n", title.bold().cyan())
+        }
+        _ => println!("{}\n", glyphs(args, &format!("📦 {}", title))),
+    }
+}
+
+/// Text as it is, or with its glyphs replaced by ASCII ones with `--ascii`.
+fn glyphs<'a>(args: &Args, text: &'a str) -> Cow<'a, str> {
+    match args.ascii {
+        true => Cow::Owned(text::ascii(text)),
+        false => Cow::Borrowed(text),
+    }
+}
+
+/// What the report of a single project found.
+struct ProjectOutcome {
+    /// Whether the report lists any dependencies.
+    has_updates: bool,
+    /// Packages that could not be fetched, except optional ones, in the order of `package.json`.
+    failed: Vec<String>,
+}
+
+/// Print the report of a single project.
+async fn report_project(
+    args: &Args,
+    path: &PathBuf,
+    client: &Client,
+    cache: &mut Option<MetadataCache>,
+    limits: FetchLimits,
+) -> Result<ProjectOutcome, Error> {
+    let project = Project::new(path)?;
+    let is_grouped = args.projects.is_some() || args.workspaces;
+    if is_grouped {
+        print_project_header(args, path, &project);
+    }
+    for warning in project.warnings() {
+        eprintln!(
+            "{}",
+            glyphs(args, &format!("⚠ {}: {}", path.display(), warning))
+        );
+    }
+    let update_policy = UpdatePolicy::new(args, &project, path)?;
+    let registries = args.registries(&project);
+    // Metadata of another registry may list other versions, so a project with other registries starts over
+    let cache = match cache {
+        Some(cache) if cache.registries == registries => cache,
+        _ => cache.insert(MetadataCache::new(args, registries.clone())),
+    };
+
+    let selection = Selection::new(args, &project)?;
+    let fetched_packages = fetch_cached(
+        client,
+        &registries,
+        &project,
+        &selection,
+        &update_policy,
+        cache,
+        limits,
+    )
+    .await;
+
+    let format = args.report_format();
+    let mut entries = match format {
+        ReportFormat::NpmOutdated => {
+            behind_wanted_or_latest(&project, &selection, &fetched_packages)
+        }
+        _ => outdated(&project, &selection, &fetched_packages),
+    };
+    // The names are fed to install commands, so they only list what is updated
+    if format == ReportFormat::Names {
+        entries.retain(|dependency| selection.updates(dependency.kind));
+    }
+    let output = match format {
+        ReportFormat::Text => text::plain(&entries),
+        ReportFormat::Markdown => text::markdown(&entries),
+        ReportFormat::NpmOutdated => text::npm_outdated(&entries),
+        ReportFormat::Grouped => text::grouped(&entries),
+        ReportFormat::Csv => text::csv(&entries),
+        ReportFormat::Names => text::names(&entries),
+        ReportFormat::Json => text::json(&entries),
+    };
+    print!("{}", glyphs(args, &output));
+
+    if format == ReportFormat::Text {
+        let is_fetched = selection
+            .names(&project)
+            .all(|name| fetched_packages.contains_key(name));
+        if entries.is_empty() && is_fetched {
+            println!("{}", glyphs(args, text::ALL_UP_TO_DATE));
+        }
+        for dependency in unpublished(&project, &selection, &fetched_packages) {
+            let warning = format!(
+                "⚠ {} {}: current version no longer published",
+                dependency.name, dependency.declared
+            );
+            println!("{}", glyphs(args, &warning));
+        }
+
+        print_pinned_tools(args, &project, client, &registries, limits).await;
+
+        let overrides = project.overrides();
+        if !overrides.is_empty() {
+            println!("Overrides:");
+            for pin in overrides {
+                println!("  {}  {}", pin.path.join(" > "), pin.version);
+            }
+        }
+
+        let trusted_dependencies = project.trusted_dependencies();
+        if !trusted_dependencies.is_empty() {
+            println!("Trusted dependencies: {}", trusted_dependencies.join(", "));
+        }
+    }
+    if is_grouped && format != ReportFormat::Names {
+        println!();
+    }
+
+    let failed = failed_packages(&project, &selection, &fetched_packages);
+
+    Ok(ProjectOutcome {
+        has_updates: entries
+            .iter()
+            .any(|dependency| selection.updates(dependency.kind)),
+        failed,
+    })
+}
+
+/// Print the tools pinned by the project with their latest versions.
+async fn print_pinned_tools(
+    args: &Args,
+    project: &Project,
+    client: &Client,
+    registries: &Registries,
+    limits: FetchLimits,
+) {
+    let pinned_tools = project.pinned_tools();
+    if pinned_tools.is_empty() {
+        return;
+    }
+
+    // The `node` package in the registry is not Node itself, so its latest version is meaningless
+    let names: Vec<String> = pinned_tools
+        .iter()
+        .map(|tool| tool.name.clone())
+        .filter(|name| name != "node")
+        .collect();
+    let latest_versions: HashMap<&String, String> =
+        registry::fetch_all(client, registries, &names, limits)
+            .filter_map(
+                |(name, metadata)| async move { Some((name, metadata.ok()?.dist_tags.latest)) },
+            )
+            .collect()
+            .await;
+
+    println!("Pinned tools:");
+    for tool in &pinned_tools {
+        let latest = match latest_versions.get(&tool.name) {
+            Some(latest) if !is_same_version(&tool.version, latest) => format!("  →  {}", latest),
+            Some(_) => "  up to date".to_owned(),
+            None => String::new(),
+        };
+        let line = format!(
+            "  {} {} ({}){}",
+            tool.name, tool.version, tool.field, latest
+        );
+        println!("{}", glyphs(args, &line));
+    }
+}
+
+fn is_same_version(pinned: &str, latest: &str) -> bool {
+    match version::current_version(pinned) {
+        Some(pinned) => version::is_up_to_date(&pinned, latest),
+        None => pinned == latest,
+    }
+}
+
+/// Registry metadata by package name, shared by the projects of a report so that common dependencies are fetched once.
+struct MetadataCache {
+    packages: HashMap<String, Metadata>,
+    /// Registries that the packages are fetched from.
+    registries: Registries,
+    /// Where fetched packages are persisted as they arrive, so that an interrupted run can resume.
+    disk_cache: Option<DiskCache>,
+}
+
+impl MetadataCache {
+    /// Cache that starts with the packages fetched by recent runs.
+    fn new(args: &Args, registries: Registries) -> Self {
+        let disk_cache = DiskCache::new(args, registries.clone());
+        let packages = disk_cache.as_ref().map(DiskCache::load).unwrap_or_default();
+
+        MetadataCache {
+            packages,
+            registries,
+            disk_cache,
+        }
+    }
+
+    fn insert(&mut self, name: &str, metadata: Metadata) {
+        if let Some(disk_cache) = &self.disk_cache {
+            // The report is still complete without the disk cache, so only warn about it once
+            if let Err(e) = disk_cache.insert(&metadata) {
+                eprintln!("Unable to write the metadata cache: {}", e);
+                self.disk_cache = None;
+            }
+        }
+        self.packages.insert(name.to_owned(), metadata);
+    }
+}
+
+/// Fetch the version that each selected dependency should be updated to, reporting failed fetches on stderr.
+pub async fn fetch_update_targets(
+    args: &Args,
+    project: &Project,
+    selection: &Selection,
+    update_policy: &UpdatePolicy,
+) -> Result<HashMap<String, FetchedPackage>, Error> {
+    let client = args.client()?;
+    let registries = args.registries(project);
+    let fetched_packages = fetch_cached(
+        &client,
+        &registries,
+        project,
+        selection,
+        update_policy,
+        &mut MetadataCache::new(args, registries.clone()),
+        args.fetch_limits(),
+    )
+    .await;
+
+    Ok(fetched_packages)
+}
+
+/// Like `fetch_update_targets`, but only fetches packages that are missing from the `cache`.
+async fn fetch_cached(
+    client: &Client,
+    registries: &Registries,
+    project: &Project,
+    selection: &Selection,
+    update_policy: &UpdatePolicy,
+    cache: &mut MetadataCache,
+    limits: FetchLimits,
+) -> HashMap<String, FetchedPackage> {
+    let missing: Vec<String> = selection
+        .names(project)
+        .filter(|name| !cache.packages.contains_key(*name))
+        .cloned()
+        .collect();
+
+    // Every package fails the same way if the registry can't be reached, so report such errors once below
+    let mut registry_errors: Vec<(String, usize)> = Vec::new();
+    let mut package_updates = registry::fetch_all(client, registries, &missing, limits);
+    while let Some((name, package)) = package_updates.next().await {
+        match package {
+            Ok(package) => {
+                if let Some(latest) = package.unlisted_latest() {
+                    eprintln!(
+                        "ℹ {} {} is tagged as latest but not listed yet, the registry may be syncing",
+                        name, latest
+                    );
+                }
+                cache.insert(name, package);
+            }
+            Err(e) if e.is_registry_wide() => {
+                let message = e.to_string();
+                match registry_errors.iter_mut().find(|(m, _)| *m == message) {
+                    Some((_, count)) => *count += 1,
+                    None => registry_errors.push((message, 1)),
+                }
+            }
+            // Optional packages may legitimately be missing from the registry
+            Err(e) if project.is_optional(name) => {
+                eprintln!("ℹ Skipped optional package {}: {}", name, e)
+            }
+            Err(e) => eprintln!("Unable to fetch {}: {}", name, e),
+        }
+    }
+
+    for (message, count) in registry_errors {
+        match count {
+            1 => eprintln!("Unable to fetch 1 package: {}", message),
+            _ => eprintln!("Unable to fetch {} packages: {}", count, message),
+        }
+    }
+
+    selection
+        .names(project)
+        .filter_map(|name| {
+            let metadata = cache.packages.get(name)?;
+            let ranges = selection.declared_ranges(project, name);
+            let fetched = FetchedPackage::with_ranges(metadata, &ranges, update_policy);
+            Some((name.to_owned(), fetched))
+        })
+        .collect()
+}
+
+/// Selected packages that could not be fetched, except optional ones that may be missing from the registry.
+fn failed_packages(
+    project: &Project,
+    selection: &Selection,
+    fetched_packages: &HashMap<String, FetchedPackage>,
+) -> Vec<String> {
+    selection
+        .names(project)
+        .filter(|name| !fetched_packages.contains_key(*name) && !project.is_optional(name))
+        .cloned()
+        .collect()
+}
+
+/// Dependencies with an available update, in the order of `package.json`.
+pub fn outdated<'a>(
+    project: &'a Project,
+    selection: &'a Selection,
+    fetched_packages: &'a HashMap<String, FetchedPackage>,
+) -> Vec<Dependency<'a>> {
+    all(project, selection, fetched_packages)
+        .filter(|dependency| dependency.severity.is_some())
+        .collect()
+}
+
+/// Dependencies older than their wanted or latest version, which `npm outdated` lists regardless of `--in-range`.
+fn behind_wanted_or_latest<'a>(
+    project: &'a Project,
+    selection: &'a Selection,
+    fetched_packages: &'a HashMap<String, FetchedPackage>,
+) -> Vec<Dependency<'a>> {
+    all(project, selection, fetched_packages)
+        .filter(
+            |dependency| match (&dependency.current, dependency.fetched) {
+                (Some(current), Some(fetched)) => {
+                    !version::is_up_to_date(current, &fetched.wanted)
+                        || !version::is_up_to_date(current, &fetched.latest)
+                }
+                _ => false,
+            },
+        )
+        .collect()
+}
+
+/// Dependencies whose declared version is no longer published, in the order of `package.json`.
+fn unpublished<'a>(
+    project: &'a Project,
+    selection: &'a Selection,
+    fetched_packages: &'a HashMap<String, FetchedPackage>,
+) -> Vec<Dependency<'a>> {
+    all(project, selection, fetched_packages)
+        .filter(|dependency| dependency.unpublished)
+        .collect()
+}
+
+fn all<'a>(
+    project: &'a Project,
+    selection: &'a Selection,
+    fetched_packages: &'a HashMap<String, FetchedPackage>,
+) -> impl Iterator<Item = Dependency<'a>> {
+    selection.tables.iter().flat_map(|&table| {
+        Dependency::list(
+            project,
+            selection,
+            fetched_packages,
+            table,
+            SortMode::Manifest,
+            &HashSet::new(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn only_fails_strict_reports_for_required_packages() {
+        let project = Project::parse(
+            r#"{
+                "dependencies": { "left-pad": "^1.3.0" },
+                "devDependencies": { "typescript": "^5.0.0" },
+                "peerDependencies": { "typescript": ">=4", "react-native": "*" },
+                "peerDependenciesMeta": { "typescript": { "optional": true }, "react-native": { "optional": true } },
+                "optionalDependencies": { "fsevents": "^2.3.0" }
+            }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat", "--strict"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+
+        // Every package 404s, but the optional ones may legitimately be missing
+        let failed = failed_packages(&project, &selection, &HashMap::new());
+        assert_eq!(failed, ["left-pad", "typescript"]);
+        assert_eq!(exit_code(args.strict, false, &failed), ExitCode::FAILURE);
+
+        let optional_only =
+            Project::parse(r#"{ "optionalDependencies": { "fsevents": "^2.3.0" } }"#).unwrap();
+        let selection = Selection::new(&args, &optional_only).unwrap();
+        let failed = failed_packages(&optional_only, &selection, &HashMap::new());
+        assert!(failed.is_empty());
+        assert_eq!(exit_code(args.strict, false, &failed), ExitCode::SUCCESS);
+    }
+}
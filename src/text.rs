@@ -0,0 +1,515 @@
+//! Render a report of outdated dependencies as text.
+
+use std::{collections::HashSet, fmt::Write, time::Duration};
+
+use serde_json::{json, Value};
+
+use crate::{
+    application::DependencyTable, dependency::Dependency, project::Project, version::VersionSection,
+};
+
+/// Shown once every package is fetched without finding an update, so that there's no doubt the check worked.
+pub const ALL_UP_TO_DATE: &str = "✓ All dependencies are up to date";
+
+/// Marks dependencies that ship inside the published package, see `Project::is_bundled`.
+pub const BUNDLED: &str = "📦 bundled";
+
+/// Human-readable report with a section per dependency group.
+pub fn plain(entries: &[Dependency]) -> String {
+    let name_width = column_width(entries, |e| e.name);
+    let current_width = column_width(entries, |e| e.declared);
+    let latest_width = column_width(entries, |e| e.latest.unwrap_or_default());
+
+    let mut output = String::new();
+    for (table, title) in [
+        (DependencyTable::Runtime, "Dependencies"),
+        (DependencyTable::Dev, "🔧 Development Dependencies"),
+        (DependencyTable::Peer, "🤝 Peer Dependencies"),
+        (DependencyTable::Optional, "🧩 Optional Dependencies"),
+        (DependencyTable::Overrides, "📌 Overrides"),
+    ] {
+        // Only entries with an update have a severity to show
+        let mut section_entries = entries
+            .iter()
+            .filter(|e| e.kind == table)
+            .filter_map(|e| Some((e, e.severity?)))
+            .peekable();
+        if section_entries.peek().is_none() {
+            continue;
+        }
+
+        writeln!(output, "{}", title).unwrap();
+        for (entry, severity) in section_entries {
+            write!(
+                output,
+                "  {:name_width$}  {:current_width$}  →  {:latest_width$}  {}",
+                entry.name,
+                entry.declared,
+                entry.latest.unwrap_or_default(),
+                severity,
+            )
+            .unwrap();
+            if entry.optional {
+                write!(output, " (optional peer)").unwrap();
+            }
+            if entry.bundled {
+                write!(output, " {}", BUNDLED).unwrap();
+            }
+            writeln!(output).unwrap();
+        }
+        writeln!(output).unwrap();
+    }
+    writeln!(output, "{}", summary(entries)).unwrap();
+
+    output
+}
+
+/// Markdown table to be pasted into pull request descriptions.
+pub fn markdown(entries: &[Dependency]) -> String {
+    let mut output =
+        String::from("| Package | Current | Latest | Type |\n| --- | --- | --- | --- |\n");
+    for entry in entries {
+        writeln!(
+            output,
+            "| `{}` | `{}` | `{}` | {} |",
+            entry.name,
+            entry.declared,
+            entry.latest.unwrap_or_default(),
+            entry.kind.key(),
+        )
+        .unwrap();
+    }
+    writeln!(output, "\n**{}**", summary(entries)).unwrap();
+
+    output
+}
+
+/// `npm install` commands that apply the updates, one for each dependency group.
+pub fn install_commands(entries: &[Dependency]) -> String {
+    let mut output = String::new();
+    for (table, command) in [
+        (DependencyTable::Runtime, "npm install"),
+        (DependencyTable::Dev, "npm install --save-dev"),
+        (DependencyTable::Peer, "npm install --save-peer"),
+        (DependencyTable::Optional, "npm install --save-optional"),
+    ] {
+        let packages: Vec<String> = entries
+            .iter()
+            .filter(|e| e.kind == table)
+            .map(|e| format!("{}@{}", e.name, e.latest.unwrap_or_default()))
+            .collect();
+        if !packages.is_empty() {
+            writeln!(output, "{} {}", command, packages.join(" ")).unwrap();
+        }
+    }
+
+    output
+}
+
+/// Pull request description with the Markdown table of updates and the commands that apply them.
+pub fn pull_request_body(entries: &[Dependency]) -> String {
+    format!(
+        "{}\n```sh\n{}```\n",
+        markdown(entries),
+        install_commands(entries)
+    )
+}
+
+/// Updates grouped by version section like Renovate groups pull requests, safest group first.
+pub fn grouped(entries: &[Dependency]) -> String {
+    let mut output = String::new();
+    for section in [
+        VersionSection::Patch,
+        VersionSection::Minor,
+        VersionSection::PreV1,
+        VersionSection::Major,
+    ] {
+        let group: Vec<_> = entries
+            .iter()
+            .filter(|e| e.severity == Some(section))
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+
+        writeln!(output, "{} ({})", section, group.len()).unwrap();
+        for entry in group {
+            writeln!(
+                output,
+                "  {} {} → {}",
+                entry.name,
+                entry.declared,
+                entry.latest.unwrap_or_default()
+            )
+            .unwrap();
+        }
+        writeln!(output).unwrap();
+    }
+    writeln!(output, "{}", summary(entries)).unwrap();
+
+    output
+}
+
+/// Before and after lines of the versions that differ between two states of `package.json`, grouped by field.
+///
+/// Comparing the projects themselves rather than the planned changes shows exactly what writing `after` changes.
+pub fn diff(before: &Project, after: &Project) -> String {
+    let mut output = String::new();
+    // The npm imports of a `deno.json` file are rewritten in place, so show the whole specifiers
+    if before.is_deno() {
+        let changes: Vec<_> = after.values["imports"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, to)| {
+                let from = before.values["imports"].get(key)?;
+                (from != to).then(|| (key.to_owned(), from.to_string(), to.to_string()))
+            })
+            .collect();
+        diff_section(&mut output, "imports", &changes);
+
+        return output;
+    }
+
+    // Overrides may be nested, so they are compared pin by pin below
+    for table in DependencyTable::ALL
+        .into_iter()
+        .filter(|&table| table != DependencyTable::Overrides)
+    {
+        let changes: Vec<_> = after
+            .dependency_group(table.key())
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, to)| {
+                let from = before.dependency_group(table.key())?.get(name)?;
+                (from != to).then(|| (name.to_owned(), from.to_string(), to.to_string()))
+            })
+            .collect();
+        diff_section(&mut output, table.key(), &changes);
+    }
+
+    let before_overrides = before.overrides();
+    let override_changes: Vec<_> = after
+        .overrides()
+        .into_iter()
+        .filter_map(|pin| {
+            let from = before_overrides.iter().find(|p| p.pointer == pin.pointer)?;
+            (from.version != pin.version).then(|| {
+                let quote = |version: &str| Value::from(version).to_string();
+                (
+                    pin.path.join(" > "),
+                    quote(&from.version),
+                    quote(&pin.version),
+                )
+            })
+        })
+        .collect();
+    diff_section(&mut output, "overrides", &override_changes);
+
+    output
+}
+
+/// Lines of the `(name, from, to)` changes of a field, where versions are JSON strings.
+fn diff_section(output: &mut String, key: &str, changes: &[(String, String, String)]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    writeln!(output, "  \"{}\": {{", key).unwrap();
+    for (name, from, to) in changes {
+        writeln!(output, "-     \"{}\": {}", name, from).unwrap();
+        writeln!(output, "+     \"{}\": {}", name, to).unwrap();
+    }
+    writeln!(output, "  }}").unwrap();
+}
+
+/// Comma-separated values with a header line, one row per update.
+pub fn csv(entries: &[Dependency]) -> String {
+    let mut output = String::from("name,current,latest,type,severity\n");
+    for entry in entries {
+        let fields = [
+            entry.name.to_owned(),
+            entry.declared.to_owned(),
+            entry.latest.unwrap_or_default().to_owned(),
+            entry.kind.key().to_owned(),
+            entry
+                .severity
+                .map(|severity| severity.to_string())
+                .unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(output, "{}", fields.join(",")).unwrap();
+    }
+
+    output
+}
+
+/// Pretty-printed JSON array with an object per update, with the fields of the CSV columns.
+pub fn json(entries: &[Dependency]) -> String {
+    let updates: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "name": entry.name,
+                "current": entry.declared,
+                "latest": entry.latest,
+                "type": entry.kind.key(),
+                "severity": entry.severity.map(|severity| severity.to_string()),
+            })
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&updates).expect("JSON values are serializable") + "\n"
+}
+
+/// Names of the updated packages, one per line, listing a package declared in several tables once.
+pub fn names(entries: &[Dependency]) -> String {
+    let mut output = String::new();
+    let mut seen = HashSet::new();
+    for entry in entries {
+        if seen.insert(entry.name) {
+            writeln!(output, "{}", entry.name).unwrap();
+        }
+    }
+
+    output
+}
+
+/// Field quoted as described in RFC 4180 if it contains a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Table with the columns of `npm outdated`, where "Current" is the lowest version of the declared range.
+pub fn npm_outdated(entries: &[Dependency]) -> String {
+    let rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|e| {
+            let current = match &e.current {
+                Some(current) => current.to_string(),
+                None => e.declared.to_owned(),
+            };
+            let (wanted, latest) = match e.fetched {
+                Some(fetched) => (fetched.wanted.clone(), fetched.latest.clone()),
+                None => Default::default(),
+            };
+
+            [
+                e.name.to_owned(),
+                current,
+                wanted,
+                latest,
+                format!("node_modules/{}", e.name),
+            ]
+        })
+        .collect();
+    if rows.is_empty() {
+        return String::new();
+    }
+
+    let header = ["Package", "Current", "Wanted", "Latest", "Location"].map(str::to_owned);
+    let mut widths = [0; 5];
+    for row in [&header].into_iter().chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut output = String::new();
+    for row in [&header].into_iter().chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell))
+            .collect();
+        writeln!(output, "{}", line.join("  ").trim_end()).unwrap();
+    }
+
+    output
+}
+
+/// Number of available updates in total and per version section, e.g. `3 updates: 1 major, 2 minor`.
+fn summary(entries: &[Dependency]) -> String {
+    let counts: Vec<String> = [
+        VersionSection::Major,
+        VersionSection::PreV1,
+        VersionSection::Minor,
+        VersionSection::Patch,
+    ]
+    .iter()
+    .filter_map(|section| {
+        let count = entries
+            .iter()
+            .filter(|e| e.severity == Some(*section))
+            .count();
+        (count != 0).then(|| format!("{} {}", count, section))
+    })
+    .collect();
+
+    match entries.len() {
+        0 => "0 updates".to_owned(),
+        1 => format!("1 update: {}", counts.join(", ")),
+        n => format!("{} updates: {}", n, counts.join(", ")),
+    }
+}
+
+/// ASCII stand-in of a glyph that some terminals or fonts show garbled, for `--ascii`.
+pub fn ascii_glyph(c: char) -> Option<&'static str> {
+    let glyph = match c {
+        '→' => "->",
+        '↑' => "^",
+        '↓' => "v",
+        '✓' => "+",
+        '⚠' => "!",
+        'ℹ' => "i",
+        '…' => "...",
+        '·' | '—' | '–' => "-",
+        // Borders of every `BorderType`
+        '─' | '━' | '═' => "-",
+        '│' | '┃' | '║' => "|",
+        '╭' | '╮' | '╰' | '╯' | '┌' | '┐' | '└' | '┘' | '┏' | '┓' | '┗' | '┛' | '╔' | '╗' | '╚'
+        | '╝' => "+",
+        // Bars of gauges
+        '█' | '▉' | '▊' | '▋' | '▌' | '▍' | '▎' | '▏' => "#",
+        // Emoji only decorate headings
+        '📦' | '🔧' | '🤝' | '📌' => "",
+        _ => return None,
+    };
+
+    Some(glyph)
+}
+
+/// Text with the glyphs known to `ascii_glyph` replaced, other characters are kept.
+pub fn ascii(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match ascii_glyph(c) {
+            // Along with the space that separates the emoji from the text
+            Some("") => {
+                chars.next_if_eq(&' ');
+            }
+            Some(glyph) => output.push_str(glyph),
+            None => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// How long ago something happened, in its largest unit, e.g. `2 days ago`.
+pub fn ago(elapsed: Duration) -> String {
+    let minutes = elapsed.as_secs() / 60;
+    let (count, unit) = match minutes {
+        0 => return "moments ago".to_owned(),
+        1..=59 => (minutes, "minute"),
+        60..=1439 => (minutes / 60, "hour"),
+        _ => (minutes / 1440, "day"),
+    };
+
+    match count {
+        1 => format!("1 {} ago", unit),
+        _ => format!("{} {}s ago", count, unit),
+    }
+}
+
+fn column_width<'a>(
+    entries: &[Dependency<'a>],
+    column: impl Fn(&Dependency<'a>) -> &'a str,
+) -> usize {
+    entries
+        .iter()
+        .map(|e| column(e).chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_csv_fields_when_needed() {
+        assert_eq!(csv_field("1.0.0+build.1"), "1.0.0+build.1");
+        assert_eq!(csv_field(">=1.0.0, <2.0.0"), "\">=1.0.0, <2.0.0\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn replaces_glyphs_with_ascii() {
+        assert_eq!(
+            ascii("🔧 Development Dependencies\n  eslint  ^8.0.0  →  ^9.0.0  ⚠ major\n"),
+            "Development Dependencies\n  eslint  ^8.0.0  ->  ^9.0.0  ! major\n"
+        );
+        assert_eq!(ascii("Fetching día…"), "Fetching día...");
+    }
+
+    #[test]
+    fn tells_how_long_ago_in_the_largest_unit() {
+        assert_eq!(ago(Duration::from_secs(30)), "moments ago");
+        assert_eq!(ago(Duration::from_secs(60)), "1 minute ago");
+        assert_eq!(ago(Duration::from_secs(3 * 3600 + 59)), "3 hours ago");
+        assert_eq!(ago(Duration::from_secs(2 * 86400 + 7200)), "2 days ago");
+    }
+
+    #[test]
+    fn serializes_updates_as_json() {
+        let entries = [Dependency {
+            name: "react",
+            declared: "^17.0.2",
+            current: None,
+            is_installed: false,
+            kind: DependencyTable::Runtime,
+            latest: Some("18.2.0"),
+            fetched: None,
+            severity: Some(VersionSection::Major),
+            marked: false,
+            unpublished: false,
+            optional: false,
+            bundled: false,
+            pointer: None,
+        }];
+        let updates: Value = serde_json::from_str(&json(&entries)).unwrap();
+
+        assert_eq!(
+            updates,
+            json!([{
+                "name": "react",
+                "current": "^17.0.2",
+                "latest": "18.2.0",
+                "type": "dependencies",
+                "severity": VersionSection::Major.to_string(),
+            }])
+        );
+    }
+
+    #[test]
+    fn lists_each_outdated_name_once() {
+        let dependency = |name, kind| Dependency {
+            name,
+            declared: "^1.0.0",
+            current: None,
+            is_installed: false,
+            kind,
+            latest: Some("2.0.0"),
+            fetched: None,
+            severity: Some(VersionSection::Major),
+            marked: false,
+            unpublished: false,
+            optional: false,
+            bundled: false,
+            pointer: None,
+        };
+        let entries = [
+            dependency("react", DependencyTable::Dev),
+            dependency("react", DependencyTable::Peer),
+            dependency("left-pad", DependencyTable::Dev),
+        ];
+
+        assert_eq!(names(&entries), "react\nleft-pad\n");
+    }
+}
@@ -1,23 +1,35 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use clap::ArgEnum;
 use tui::{
     backend::Backend,
-    layout::{Constraint, Direction, Layout},
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Cell, Gauge, Paragraph, Row, Table},
+    widgets::{
+        Block, BorderType, Borders, Cell, Clear, Gauge, Paragraph, Row, Table, TableState, Widget,
+        Wrap,
+    },
     Frame,
 };
 
 use crate::{
-    application::{DependencyTable, State},
+    application::{
+        Confirmation, DependencyTable, Dependents, ReleaseNotes, SortMode, State, Toast,
+        VisibleColumns,
+    },
+    dependency::{Dependency, FetchedPackage, Selection},
     project::Project,
+    registry, text, update,
+    version::{self, VersionSection},
 };
 
 pub fn draw_ui<B: Backend>(
     f: &mut Frame<B>,
     project: &Project,
-    fetched_packages: &HashMap<String, String>,
+    selection: &Selection,
+    fetched_packages: &HashMap<String, FetchedPackage>,
     state: &mut State,
 ) {
     let root = Layout::default()
@@ -27,55 +39,417 @@ pub fn draw_ui<B: Backend>(
             [
                 Constraint::Length(3),
                 Constraint::Min(0),
+                Constraint::Length(1),
                 Constraint::Length(3),
             ]
             .as_ref(),
         )
         .split(f.size());
 
+    // Tables without dependencies are hidden, the others share the height evenly
+    let visible_tables: Vec<_> = state.visible_tables().collect();
+
+    // Each shown table gets a small gauge next to the overall status, so that a lagging one stands out
     let header = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(25), Constraint::Percentage(75)].as_ref())
+        .constraints(
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(if visible_tables.is_empty() { 75 } else { 35 }),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
         .split(root[0]);
     f.render_widget(project_info(project), header[0]);
     f.render_widget(loading_progress(state, fetched_packages), header[1]);
+    let table_gauges = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![
+            Constraint::Ratio(1, visible_tables.len().max(1) as u32);
+            visible_tables.len()
+        ])
+        .split(header[2]);
+    for (&table, area) in visible_tables.iter().zip(table_gauges) {
+        let gauge = table_progress(project, selection, fetched_packages, state, table);
+        f.render_widget(gauge, area);
+    }
+
+    let mut tables_area = root[1];
+    let is_up_to_date = is_all_up_to_date(project, selection, fetched_packages, state);
+    if is_up_to_date {
+        let banner = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+            .split(root[1]);
+        f.render_widget(
+            Paragraph::new(text::ALL_UP_TO_DATE).style(
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            banner[0],
+        );
+        tables_area = banner[1];
+    }
 
+    let table_count = visible_tables.len().max(1) as u32;
     let main = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(root[1]);
-    if state.dependencies_len != 0 {
-        f.render_stateful_widget(
-            dependencies_table(project, fetched_packages, state, DependencyTable::Runtime),
-            main[0],
-            &mut state.dependencies_table_state,
+        .constraints(vec![
+            Constraint::Ratio(1, table_count);
+            visible_tables.len()
+        ])
+        .split(tables_area);
+    if let (true, false, Some(min_severity)) =
+        (visible_tables.is_empty(), is_up_to_date, state.min_severity)
+    {
+        let message = format!(
+            "No {} updates or above yet, press f to show every dependency",
+            min_severity
+        );
+        f.render_widget(
+            Paragraph::new(message).style(Style::default().fg(Color::DarkGray)),
+            root[1],
         );
     }
-    if state.dev_dependencies_len != 0 {
-        let area = if state.dependencies_len != 0 {
-            main[1]
-        } else {
-            main[0]
-        };
-        f.render_stateful_widget(
-            dependencies_table(project, fetched_packages, state, DependencyTable::Dev),
-            area,
-            &mut state.dev_dependencies_table_state,
+    for (dependency_type, area) in visible_tables.into_iter().zip(main) {
+        let mut dependencies = Dependency::list(
+            project,
+            selection,
+            fetched_packages,
+            dependency_type,
+            state.sort_mode,
+            state.update_index(dependency_type),
+        );
+        dependencies.retain(|dependency| state.shows(dependency));
+        // Widths fit every row, so that columns don't jump while scrolling
+        let widths = column_widths(&dependencies, area.width, state.visible_columns);
+
+        // Only the rows in view are built, so that large projects render as fast as small ones
+        let view = state.table_mut(dependency_type);
+        let selected = view.table_state.selected();
+        let height = usize::from(area.height.saturating_sub(2));
+        view.offset = scroll_offset(view.offset, selected, height, dependencies.len());
+        let offset = view.offset;
+        let visible_rows = dependencies.into_iter().skip(offset).take(height).collect();
+
+        let table = dependencies_table(
+            project,
+            selection,
+            visible_rows,
+            state,
+            dependency_type,
+            &widths,
         );
+        let mut window_state = TableState::default();
+        window_state.select(selected.map(|selected| selected - offset));
+        f.render_stateful_widget(table, area, &mut window_state);
     }
 
-    f.render_widget(help(), root[2]);
+    f.render_widget(
+        severity_summary(project, selection, fetched_packages, state),
+        root[2],
+    );
+    match &state.toast {
+        Some(toast) => f.render_widget(toast_message(toast), root[3]),
+        None => f.render_widget(help(), root[3]),
+    }
+
+    if let Some(dependents) = &state.dependents {
+        let area = centered_rect(60, 50, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(dependents_popup(dependents), area);
+    }
+    if state.shows_fetch_errors {
+        let area = centered_rect(70, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(fetch_errors_popup(state), area);
+    }
+    if let Some(release_notes) = &state.release_notes {
+        let area = centered_rect(70, 70, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(release_notes_popup(release_notes), area);
+    }
+    if let Some(confirmation) = &state.confirmation {
+        let area = centered_rect(60, 60, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(confirmation_popup(confirmation, project), area);
+    }
+
+    // Replaced last, so that borders and symbols of every widget are covered
+    if state.ascii {
+        f.render_widget(AsciiOnly, f.size());
+    }
+}
+
+/// Replaces the symbols drawn so far with their ASCII stand-ins, see `text::ascii_glyph`.
+struct AsciiOnly;
+
+impl Widget for AsciiOnly {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                let mut chars = cell.symbol.chars();
+                let glyph = match (chars.next(), chars.next()) {
+                    (Some(c), None) => text::ascii_glyph(c),
+                    _ => None,
+                };
+                // A cell holds a single character, so `->` becomes `-`
+                if let Some(glyph) = glyph {
+                    let replacement = &glyph[..glyph.len().min(1)];
+                    cell.set_symbol(if replacement.is_empty() {
+                        " "
+                    } else {
+                        replacement
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn is_all_up_to_date(
+    project: &Project,
+    selection: &Selection,
+    fetched_packages: &HashMap<String, FetchedPackage>,
+    state: &State,
+) -> bool {
+    let is_fetched = state.fetch_len != 0
+        && state.error_count() == 0
+        && fetched_packages.len() + state.fetch_errors.len() >= state.fetch_len;
+
+    is_fetched
+        && DependencyTable::ALL.into_iter().all(|table| {
+            Dependency::list(
+                project,
+                selection,
+                fetched_packages,
+                table,
+                SortMode::Manifest,
+                &HashSet::new(),
+            )
+            .iter()
+            .all(|dependency| dependency.severity.is_none())
+        })
+}
+
+/// First row to show of a table with `len` rows and room for `height` rows, scrolled as little as possible from
+/// the previous `offset` to show the `selected` row.
+fn scroll_offset(offset: usize, selected: Option<usize>, height: usize, len: usize) -> usize {
+    let offset = offset.min(len.saturating_sub(height));
+    match selected {
+        Some(selected) if selected < offset => selected,
+        Some(selected) if selected >= offset + height => selected + 1 - height,
+        _ => offset,
+    }
+}
+
+/// Area of the given percentage size in the middle of `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
+
+fn dependents_popup(dependents: &Dependents) -> Paragraph<'_> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let list = |names: &[String]| match names.is_empty() {
+        true => "none".to_owned(),
+        false => names.join(", "),
+    };
+
+    let text = match &dependents.lookup {
+        Some(lookup) => vec![
+            Spans::from(Span::styled("Required by", bold)),
+            Spans::from(list(&lookup.dependents)),
+            Spans::from(""),
+            Spans::from(Span::styled("Pulled in by direct dependencies", bold)),
+            Spans::from(list(&lookup.direct_dependents)),
+        ],
+        None => vec![Spans::from("No package-lock.json found")],
+    };
+
+    Paragraph::new(text).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(Span::styled(format!("Why {}?", dependents.name), bold))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    )
+}
+
+/// Each package that failed to fetch with its error, since the status only has room for the registry-wide ones.
+fn fetch_errors_popup(state: &State) -> Paragraph<'_> {
+    let mut text: Vec<Spans> = state
+        .fetch_errors
+        .iter()
+        .map(|(name, e)| {
+            let (note, color) = match state.optional_packages.contains(name) {
+                true => (" (optional)", Color::DarkGray),
+                false => ("", Color::Red),
+            };
+            Spans::from(vec![
+                Span::styled(name.as_str(), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(note),
+                Span::raw(": "),
+                Span::styled(e.to_string(), Style::default().fg(color)),
+            ])
+        })
+        .collect();
+    text.push(Spans::from(""));
+    text.push(Spans::from(Span::styled(
+        "r: fetch all again, Esc/e: close",
+        Style::default().fg(Color::Blue),
+    )));
+
+    let title = match state.fetch_errors.len() {
+        1 => "1 package failed to fetch".to_owned(),
+        n => format!("{} packages failed to fetch", n),
+    };
+    Paragraph::new(text).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(Span::styled(
+                title,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    )
 }
 
-fn project_info(project: &Project) -> Paragraph {
-    let info = vec![Spans::from(vec![
+/// Lines of the body of each release shown, the popup is meant for a quick look rather than reading them in full.
+const RELEASE_BODY_LINES: usize = 8;
+
+fn release_notes_popup(release_notes: &ReleaseNotes) -> Paragraph<'_> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let text = match &release_notes.releases {
+        None => vec![Spans::from("Fetching releases from GitHub…")],
+        Some(Err(e)) => vec![Spans::from(Span::styled(
+            e.as_str(),
+            Style::default().fg(Color::Red),
+        ))],
+        Some(Ok(releases)) if releases.is_empty() => {
+            vec![Spans::from("No GitHub releases since the declared version")]
+        }
+        Some(Ok(releases)) => {
+            let summary = match releases.len() {
+                1 => "1 release".to_owned(),
+                n => format!("{} releases", n),
+            };
+            let mut text = vec![
+                Spans::from(format!(
+                    "{}, latest titled {}",
+                    summary,
+                    releases[0].title()
+                )),
+                Spans::from(""),
+            ];
+            for release in releases {
+                text.push(Spans::from(Span::styled(release.title(), bold)));
+                let body = release.body.as_deref().unwrap_or("");
+                let lines: Vec<&str> = body
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect();
+                for line in lines.iter().take(RELEASE_BODY_LINES) {
+                    text.push(Spans::from(*line));
+                }
+                if lines.len() > RELEASE_BODY_LINES {
+                    text.push(Spans::from(Span::styled(
+                        format!("… {} more lines", lines.len() - RELEASE_BODY_LINES),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+                text.push(Spans::from(""));
+            }
+            text
+        }
+    };
+
+    Paragraph::new(text).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(Span::styled(
+                format!("Releases of {}", release_notes.name),
+                bold,
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    )
+}
+
+/// The exact lines of `package.json` that confirming the update changes.
+fn confirmation_popup<'a>(confirmation: &Confirmation, project: &Project) -> Paragraph<'a> {
+    let updated_project = update::preview(project, &confirmation.changes);
+    let mut text: Vec<Spans> = text::diff(project, &updated_project)
+        .lines()
+        .map(|line| {
+            let color = match line.chars().next() {
+                Some('-') => Color::Red,
+                Some('+') => Color::Green,
+                _ => Color::Reset,
+            };
+            Spans::from(Span::styled(line.to_owned(), Style::default().fg(color)))
+        })
+        .collect();
+    text.push(Spans::from(""));
+    text.push(Spans::from(Span::styled(
+        "Enter/y: apply, Esc/n: cancel",
+        Style::default().fg(Color::Blue),
+    )));
+
+    let title = match confirmation.changes.len() {
+        1 => format!("Update {}? 1 change", project.file_name()),
+        n => format!("Update {}? {} changes", project.file_name(), n),
+    };
+    Paragraph::new(text).block(
+        Block::default()
+            .title(Span::styled(
+                title,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    )
+}
+
+fn project_info(project: &Project) -> Paragraph<'_> {
+    let mut info = vec![
         Span::styled(
             project.name(),
             Style::default().add_modifier(Modifier::BOLD),
         ),
         Span::raw(": "),
         Span::styled(project.version(), Style::default().fg(Color::Green)),
-    ])];
+    ];
+    for tool in project.pinned_tools() {
+        info.push(Span::styled(
+            format!(" · {}@{}", tool.name, tool.version),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    let info = vec![Spans::from(info)];
 
     Paragraph::new(info).block(
         Block::default()
@@ -87,11 +461,47 @@ fn project_info(project: &Project) -> Paragraph {
 
 fn loading_progress<'a>(
     state: &'a State,
-    fetched_packages: &'a HashMap<String, String>,
+    fetched_packages: &'a HashMap<String, FetchedPackage>,
 ) -> Gauge<'a> {
     let fetched_count = fetched_packages.len();
-    let total_count = state.dependencies_len + state.dev_dependencies_len;
-    let label = format!("{}/{}", fetched_count, total_count);
+    let completed_count = fetched_count + state.fetch_errors.len();
+    let error_count = state.error_count();
+    let total_count = state.fetch_len;
+
+    let mut label = match (completed_count < total_count, error_count) {
+        (true, 0) => format!("{}/{}", fetched_count, total_count),
+        (true, 1) => format!("{}/{} (1 error)", fetched_count, total_count),
+        (true, _) => format!("{}/{} ({} errors)", fetched_count, total_count, error_count),
+        (false, 0) => "Done".to_owned(),
+        (false, 1) => "Done with 1 error".to_owned(),
+        (false, _) => format!("Done with {} errors", error_count),
+    };
+    if completed_count < total_count {
+        label += &format!(" · {:.1} packages/sec", state.throughput());
+    }
+    let count_errors = |is_kind: fn(&registry::Error) -> bool| {
+        state
+            .fetch_errors
+            .iter()
+            .filter(|(_, e)| is_kind(e))
+            .count()
+    };
+    let timed_out_count = count_errors(|e| matches!(e, registry::Error::TimedOut));
+    if timed_out_count != 0 {
+        label += &format!(", {} timed out", timed_out_count);
+    }
+    let empty_count = count_errors(|e| matches!(e, registry::Error::Empty));
+    if empty_count != 0 {
+        label += &format!(", {} empty responses", empty_count);
+    }
+    // If the registry can't be reached every fetch fails, so explain why once instead of per package
+    if let Some((_, e)) = state
+        .fetch_errors
+        .iter()
+        .find(|(_, e)| e.is_registry_wide())
+    {
+        label += &format!(": {}", e);
+    }
 
     Gauge::default()
         .block(
@@ -101,55 +511,158 @@ fn loading_progress<'a>(
                 .border_type(BorderType::Rounded),
         )
         .gauge_style(Style::default().bg(Color::Black).fg(Color::White))
-        .ratio(fetched_count as f64 * 1.0 / total_count as f64)
+        .ratio(match total_count {
+            0 => 1.0,
+            _ => completed_count as f64 / total_count as f64,
+        })
+        .label(label)
+}
+
+fn table_progress<'a>(
+    project: &Project,
+    selection: &Selection,
+    fetched_packages: &HashMap<String, FetchedPackage>,
+    state: &State,
+    table: DependencyTable,
+) -> Gauge<'a> {
+    let title = match table {
+        DependencyTable::Runtime => "Runtime",
+        DependencyTable::Dev => "Dev",
+        DependencyTable::Peer => "Peer",
+        DependencyTable::Optional => "Optional",
+        DependencyTable::Overrides => "Overrides",
+    };
+    // Workspace links are not fetched, like in `Selection::names`
+    let names: Vec<&String> = selection
+        .dependencies(project, table)
+        .filter(|(_, declared)| !version::is_workspace_link(declared.as_str().unwrap_or_default()))
+        .map(|(name, _)| name)
+        .collect();
+    let fetched_count = names
+        .iter()
+        .filter(|name| fetched_packages.contains_key(**name))
+        .count();
+    let is_failed = |name: &str| state.fetch_errors.iter().any(|(failed, _)| failed == name);
+    let failed_count = names.iter().filter(|name| is_failed(name)).count();
+    let completed_count = fetched_count + failed_count;
+    // Optional packages may legitimately be missing, so they complete the table without counting as failed
+    let error_count = names
+        .iter()
+        .filter(|name| is_failed(name) && !state.optional_packages.contains(**name))
+        .count();
+    let total_count = names.len();
+
+    let label = match (completed_count < total_count, error_count) {
+        (true, _) => format!("{}/{}", fetched_count, total_count),
+        (false, 0) => "Done".to_owned(),
+        (false, _) => format!("Done, {} failed", error_count),
+    };
+
+    Gauge::default()
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .gauge_style(Style::default().bg(Color::Black).fg(Color::White))
+        .ratio(match total_count {
+            0 => 1.0,
+            _ => completed_count as f64 / total_count as f64,
+        })
         .label(label)
 }
 
 fn dependencies_table<'a>(
     project: &'a Project,
-    fetched_packages: &'a HashMap<String, String>,
+    selection: &Selection,
+    dependencies: Vec<Dependency<'a>>,
     state: &State,
     dependency_type: DependencyTable,
+    widths: &'a [Constraint],
 ) -> Table<'a> {
-    let (label, dependencies, len, update_index, table_state) = match dependency_type {
-        DependencyTable::Runtime => (
-            "Dependencies",
-            project.dependencies(),
-            &state.dependencies_len,
-            &state.update_index,
-            &state.dependencies_table_state,
-        ),
-        DependencyTable::Dev => (
-            "Development Dependencies",
-            project.dev_dependencies(),
-            &state.dev_dependencies_len,
-            &state.dev_update_index,
-            &state.dev_dependencies_table_state,
-        ),
+    let label = match dependency_type {
+        DependencyTable::Runtime => "Dependencies",
+        DependencyTable::Dev => "Development Dependencies",
+        DependencyTable::Peer => "Peer Dependencies",
+        DependencyTable::Optional => "Optional Dependencies",
+        DependencyTable::Overrides => "Overrides",
     };
+    let view = state.table(dependency_type);
 
-    let deps = dependencies.iter().flat_map(|d| d.iter());
-
-    let rows = deps.enumerate().map(|(i, (name, version))| {
-        let is_toggled = update_index.contains(&i);
+    let rows = dependencies.into_iter().map(|dependency| {
         let mut row_style = Style::default();
-        let mut display_name = name.to_owned();
+        let mut display_name = dependency.name.to_owned();
+        if project.is_duplicate(dependency.name) {
+            display_name = format!("⚠ {}", display_name);
+        }
+        if dependency.optional {
+            display_name += " (optional)";
+        }
+        if dependency.bundled {
+            display_name = format!("{} {}", display_name, text::BUNDLED);
+        }
 
-        if is_toggled {
+        if dependency.marked {
             row_style = Style::default()
                 .add_modifier(Modifier::BOLD)
                 .fg(Color::LightYellow);
             display_name += "*";
         }
 
-        let mut row = vec![
-            Cell::from(display_name),
-            Cell::from(version.as_str().unwrap()),
-        ];
+        let current_cell = if dependency.unpublished {
+            Cell::from(format!("⚠ {}", dependency.declared)).style(Style::default().fg(Color::Red))
+        } else {
+            Cell::from(dependency.current_label())
+        };
+        let name_cell = match state.notes.get(dependency.name) {
+            Some(note) => Cell::from(Spans::from(vec![
+                Span::raw(display_name),
+                Span::styled(
+                    format!("  {}", note),
+                    Style::default().add_modifier(Modifier::DIM),
+                ),
+            ])),
+            None => Cell::from(display_name),
+        };
+        let mut row = vec![name_cell, current_cell];
+        let visible_columns = state.visible_columns;
+
+        if visible_columns.shows_wanted() {
+            let wanted = match dependency.fetched {
+                Some(fetched) if !dependency.is_workspace_link() => fetched.wanted.as_str(),
+                _ => "",
+            };
+            row.push(Cell::from(wanted));
+        }
+
+        let latest_cell = if dependency.is_workspace_link() {
+            Some(Cell::from(WORKSPACE_LINK).style(Style::default().fg(Color::DarkGray)))
+        } else if dependency.is_up_to_date() {
+            Some(Cell::from(UP_TO_DATE).style(Style::default().fg(Color::DarkGray)))
+        } else {
+            dependency.latest.map(|latest_version| {
+                // Outdated optional peers are up to the user to install, so they don't need attention
+                let mut latest_style = Style::default();
+                if dependency.optional {
+                    latest_style = latest_style.fg(Color::DarkGray);
+                } else if let Some(section) = dependency.severity {
+                    latest_style = latest_style.fg(state.severity_colors.color(section));
+                }
+                Cell::from(latest_version).style(latest_style)
+            })
+        };
+        if let Some(latest_cell) = latest_cell.filter(|_| visible_columns.shows_latest()) {
+            row.push(latest_cell);
+        }
 
-        let latest_version = fetched_packages.get(name);
-        if let Some(latest_version) = latest_version {
-            row.push(Cell::from(latest_version.to_owned()));
+        if visible_columns.shows_range() {
+            let range_cell = match dependency.allows_latest() {
+                Some(true) => Cell::from(IN_RANGE).style(Style::default().fg(Color::Green)),
+                Some(false) => Cell::from(OUT_OF_RANGE).style(Style::default().fg(Color::Yellow)),
+                None => Cell::from(""),
+            };
+            row.push(range_cell);
         }
 
         Row::new(row).style(row_style)
@@ -167,12 +680,18 @@ fn dependencies_table<'a>(
             .fg(Color::Green);
     }
 
-    let title = format!(
+    let mut title = format!(
         "{} [{}/{}]",
         label,
-        table_state.selected().unwrap_or(0) + 1,
-        len
+        view.table_state.selected().unwrap_or(0) + 1,
+        view.len
     );
+    if let Some(min_severity) = state.min_severity {
+        title += &format!(" {} and above", min_severity);
+    }
+    if selection.compares_installed {
+        title += " installed";
+    }
 
     Table::new(rows)
         .style(Style::default().fg(Color::White))
@@ -187,18 +706,593 @@ fn dependencies_table<'a>(
                 .border_type(BorderType::Rounded)
                 .border_style(border_style),
         )
-        .widths(&[
-            Constraint::Percentage(30),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
-        ])
+        .widths(widths)
         .column_spacing(0)
         .highlight_style(highlight_style)
 }
 
+/// Text of the latest column for dependencies without an update.
+const UP_TO_DATE: &str = "up to date";
+/// Text of the latest column for dependencies on packages of the same workspace.
+const WORKSPACE_LINK: &str = "workspace link";
+/// Text of the range column when `npm update` installs the latest version.
+const IN_RANGE: &str = "✓ in range";
+/// Text of the range column when the declared range has to change to install the latest version.
+const OUT_OF_RANGE: &str = "↑ out of range";
+/// Space kept between the version columns.
+const COLUMN_PADDING: u16 = 2;
+
+/// Widths of the name and visible version columns of a table that is `width` wide, including its borders.
+///
+/// Version columns fit their longest value, up to 40% of the table each with two of them, so that declared
+/// ranges are not cut off. The name column takes the remaining width.
+fn column_widths(
+    dependencies: &[Dependency],
+    width: u16,
+    visible_columns: VisibleColumns,
+) -> Vec<Constraint> {
+    let inner_width = width.saturating_sub(2);
+
+    let current_width = dependencies
+        .iter()
+        .map(|dependency| match dependency.unpublished {
+            true => dependency.declared.chars().count() + 2,
+            false => dependency.current_label().chars().count(),
+        })
+        .max()
+        .unwrap_or(0);
+    let latest_width = dependencies
+        .iter()
+        .filter_map(|dependency| match dependency.is_workspace_link() {
+            true => Some(WORKSPACE_LINK.len()),
+            false => dependency
+                .latest
+                .map(|latest| latest.chars().count().max(UP_TO_DATE.len())),
+        })
+        .max()
+        .unwrap_or(0);
+
+    let wanted_width = dependencies
+        .iter()
+        .filter_map(|dependency| dependency.fetched)
+        .map(|fetched| fetched.wanted.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut version_widths = vec![current_width];
+    if visible_columns.shows_wanted() {
+        version_widths.push(wanted_width);
+    }
+    if visible_columns.shows_latest() {
+        version_widths.push(latest_width);
+    }
+    if visible_columns.shows_range() {
+        let range_width = dependencies
+            .iter()
+            .filter_map(|dependency| match dependency.allows_latest()? {
+                true => Some(IN_RANGE.chars().count()),
+                false => Some(OUT_OF_RANGE.chars().count()),
+            })
+            .max()
+            .unwrap_or(0);
+        version_widths.push(range_width);
+    }
+
+    let max_width = inner_width * 2 / (version_widths.len() as u16 + 3);
+    let version_widths: Vec<u16> = version_widths
+        .into_iter()
+        .map(|content_width| (content_width as u16 + COLUMN_PADDING).min(max_width))
+        .collect();
+    let name_width = inner_width.saturating_sub(version_widths.iter().sum());
+
+    [name_width]
+        .into_iter()
+        .chain(version_widths)
+        .map(Constraint::Length)
+        .collect()
+}
+
 const HELP_TEXT: &str =
-    "↑ ↓: navigate, Space/Enter: select, Tab: switch group, u: update package.json, Esc/q: close";
+    "↑ ↓: navigate, n/N: next/previous update, Space/Enter: select, a: select all updates, +/-: change target of selected, Tab: switch group, s: sort, c: columns, f: filter by severity, w: why, l: release notes, R: fetch selected again, e: fetch errors, u: update package.json (after confirming), r: update and fetch again, U: update and close, i: compare installed/declared versions, y: copy updates as JSON, p: print PR body and close, Esc/q: close";
+
+/// Number of updates of each severity in the shown tables, e.g. "3 major, 7 minor updates available", in the
+/// colors of their rows.
+///
+/// Updates hidden by the severity filter are counted too, so that the summary tells what the filter leaves out.
+fn severity_summary<'a>(
+    project: &Project,
+    selection: &Selection,
+    fetched_packages: &HashMap<String, FetchedPackage>,
+    state: &State,
+) -> Paragraph<'a> {
+    let severities: Vec<_> = state
+        .visible_tables()
+        .flat_map(|table| {
+            Dependency::list(
+                project,
+                selection,
+                fetched_packages,
+                table,
+                SortMode::Manifest,
+                &HashSet::new(),
+            )
+        })
+        .filter_map(|dependency| dependency.severity)
+        .collect();
+    if severities.is_empty() {
+        return Paragraph::new("No updates available").style(Style::default().fg(Color::DarkGray));
+    }
+
+    let mut spans = Vec::new();
+    for &section in VersionSection::value_variants() {
+        let count = severities.iter().filter(|&&s| s == section).count();
+        if count == 0 {
+            continue;
+        }
+        if !spans.is_empty() {
+            spans.push(Span::raw(", "));
+        }
+        spans.push(Span::styled(
+            format!("{} {}", count, section),
+            Style::default()
+                .fg(state.severity_colors.color(section))
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    spans.push(Span::raw(match severities.len() {
+        1 => " update available",
+        _ => " updates available",
+    }));
+
+    Paragraph::new(Spans::from(spans))
+}
+
+fn toast_message(toast: &Toast) -> Paragraph<'_> {
+    let color = if toast.is_error {
+        Color::Red
+    } else {
+        Color::Green
+    };
+
+    Paragraph::new(toast.message.as_str())
+        .style(Style::default().fg(color).add_modifier(Modifier::BOLD))
+        .wrap(Wrap { trim: true })
+}
 
 fn help<'a>() -> Paragraph<'a> {
-    Paragraph::new(HELP_TEXT).style(Style::default().fg(Color::Blue))
+    Paragraph::new(HELP_TEXT)
+        .style(Style::default().fg(Color::Blue))
+        .wrap(Wrap { trim: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use tui::{backend::TestBackend, Terminal};
+
+    use super::*;
+    use crate::{application::AfterUpdate, args::Args, update::VersionChange};
+
+    const MANIFEST: &str = r#"{
+        "name": "demo",
+        "version": "1.0.0",
+        "dependencies": { "react": "^17.0.2", "lodash": "^4.17.21", "left-pad": "1.3.0" },
+        "devDependencies": { "eslint": "^8.0.0", "typescript": "~4.5.2" }
+    }"#;
+
+    /// Render the UI like `Application` does after parsing `args` and fetching `fetched_packages`.
+    fn render(
+        manifest: &str,
+        args: &[&str],
+        fetched_packages: &HashMap<String, FetchedPackage>,
+    ) -> Buffer {
+        render_with(manifest, args, fetched_packages, |_| {})
+    }
+
+    /// Like `render`, but with changes made to the state first, e.g. by key presses.
+    fn render_with(
+        manifest: &str,
+        args: &[&str],
+        fetched_packages: &HashMap<String, FetchedPackage>,
+        change: impl FnOnce(&mut State),
+    ) -> Buffer {
+        let project = Project::parse(manifest).unwrap();
+        let args = Args::try_parse_from([&["packrat"], args].concat()).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+
+        let table_len = |table| selection.dependencies(&project, table).count();
+        let mut state = State::new(table_len, selection.names(&project).count());
+        change(&mut state);
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        terminal
+            .draw(|f| draw_ui(f, &project, &selection, fetched_packages, &mut state))
+            .unwrap();
+
+        terminal.backend().buffer().clone()
+    }
+
+    fn lines(buffer: &Buffer) -> Vec<String> {
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Position of the first occurrence of `text` in the buffer.
+    fn find(buffer: &Buffer, text: &str) -> (u16, u16) {
+        lines(buffer)
+            .iter()
+            .enumerate()
+            .find_map(|(y, line)| {
+                let byte_index = line.find(text)?;
+                Some((line[..byte_index].chars().count() as u16, y as u16))
+            })
+            .unwrap_or_else(|| panic!("{:?} is not rendered", text))
+    }
+
+    #[test]
+    fn renders_without_dependencies() {
+        let buffer = render(
+            r#"{ "name": "empty", "version": "0.1.0" }"#,
+            &[],
+            &HashMap::new(),
+        );
+        let lines = lines(&buffer);
+
+        assert!(lines[3].contains("empty: 0.1.0"));
+        assert!(lines[3].contains("Done"));
+        assert!(lines.iter().all(|line| !line.contains("Dependencies")));
+    }
+
+    #[test]
+    fn renders_only_dev_dependencies() {
+        let buffer = render(MANIFEST, &["--dev-only"], &HashMap::new());
+        let lines = lines(&buffer);
+
+        assert!(lines[5].contains("Development Dependencies [1/2]"));
+        assert!(lines[6].contains("eslint"));
+        assert!(lines[7].contains("typescript"));
+        assert!(lines.iter().all(|line| !line.contains("react")));
+    }
+
+    #[test]
+    fn hides_updates_below_min_severity() {
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.22")),
+            ("eslint".to_owned(), FetchedPackage::at("8.5.0")),
+        ]);
+        let filter =
+            |min_severity| move |state: &mut State| state.min_severity = Some(min_severity);
+
+        let buffer = render_with(
+            MANIFEST,
+            &[],
+            &fetched_packages,
+            filter(VersionSection::Minor),
+        );
+        let filtered = lines(&buffer);
+        assert!(filtered.iter().any(|line| line.contains("minor and above")));
+        for shown in ["react", "eslint"] {
+            assert!(
+                filtered.iter().any(|line| line.contains(shown)),
+                "{}",
+                shown
+            );
+        }
+        for hidden in ["lodash", "left-pad", "typescript"] {
+            assert!(
+                filtered.iter().all(|line| !line.contains(hidden)),
+                "{}",
+                hidden
+            );
+        }
+
+        // `Application` counts the rows of the tables again once the filter hides all of them
+        let only_patch = HashMap::from([("lodash".to_owned(), FetchedPackage::at("4.17.22"))]);
+        let buffer = render_with(MANIFEST, &[], &only_patch, |state| {
+            state.min_severity = Some(VersionSection::Major);
+            state.tables = Default::default();
+        });
+        let lines = lines(&buffer);
+        assert!(lines[5].contains("No major updates or above yet"));
+        assert!(lines.iter().all(|line| !line.contains("Dependencies")));
+    }
+
+    #[test]
+    fn summarizes_updates_per_severity() {
+        let buffer = render(MANIFEST, &[], &HashMap::new());
+        find(&buffer, "No updates available");
+
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.22")),
+            ("eslint".to_owned(), FetchedPackage::at("9.0.0")),
+            ("typescript".to_owned(), FetchedPackage::at("4.6.0")),
+        ]);
+        let buffer = render_with(MANIFEST, &[], &fetched_packages, |state| {
+            state.min_severity = Some(VersionSection::Major);
+        });
+
+        find(&buffer, "2 major, 1 minor, 1 patch updates available");
+        let major = find(&buffer, "2 major");
+        assert_eq!(buffer.get(major.0, major.1).fg, Color::Red);
+        let patch = find(&buffer, "1 patch");
+        assert_eq!(buffer.get(patch.0, patch.1).fg, Color::Green);
+    }
+
+    #[test]
+    fn marks_bundled_dependencies() {
+        let manifest = r#"{
+            "name": "demo",
+            "version": "1.0.0",
+            "dependencies": { "react": "^17.0.2", "lodash": "^4.17.21" },
+            "bundledDependencies": ["lodash"]
+        }"#;
+        let buffer = render(manifest, &[], &HashMap::new());
+
+        // The emoji takes two cells, the second of which is blank
+        let lines = lines(&buffer);
+        let lodash = lines.iter().find(|line| line.contains("lodash")).unwrap();
+        assert!(lodash.contains("lodash 📦  bundled"));
+        assert!(lines.iter().all(|line| !line.contains("react 📦")));
+    }
+
+    #[test]
+    fn renders_notes_after_names() {
+        let buffer = render_with(MANIFEST, &[], &HashMap::new(), |state| {
+            state.notes = HashMap::from([("typescript".to_owned(), "pinned for now".to_owned())]);
+        });
+
+        let (x, y) = find(&buffer, "typescript  pinned for now");
+        let note = buffer.get(x + 12, y);
+        assert!(note.modifier.contains(Modifier::DIM));
+        assert!(!buffer.get(x, y).modifier.contains(Modifier::DIM));
+    }
+
+    #[test]
+    fn renders_banner_once_everything_is_up_to_date() {
+        let manifest = r#"{
+            "name": "demo",
+            "version": "1.0.0",
+            "dependencies": { "react": "^18.2.0", "lodash": "^4.17.21" }
+        }"#;
+        let react = HashMap::from([("react".to_owned(), FetchedPackage::at("18.2.0"))]);
+        let buffer = render(manifest, &[], &react);
+        assert!(lines(&buffer)
+            .iter()
+            .all(|line| !line.contains(text::ALL_UP_TO_DATE)));
+
+        let mut fetched_packages = react;
+        fetched_packages.insert("lodash".to_owned(), FetchedPackage::at("4.17.21"));
+        let buffer = render(manifest, &[], &fetched_packages);
+        let up_to_date = lines(&buffer);
+        assert!(up_to_date[5].contains(text::ALL_UP_TO_DATE));
+        assert!(up_to_date[6].contains("Dependencies"));
+
+        fetched_packages.insert("lodash".to_owned(), FetchedPackage::at("4.17.22"));
+        let buffer = render(manifest, &[], &fetched_packages);
+        assert!(!lines(&buffer)[5].contains(text::ALL_UP_TO_DATE));
+    }
+
+    #[test]
+    fn renders_only_ascii_when_asked_to() {
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+            ("eslint".to_owned(), FetchedPackage::at("9.0.0")),
+        ]);
+        let buffer = render_with(MANIFEST, &[], &fetched_packages, |state| state.ascii = true);
+        let rendered = lines(&buffer);
+
+        assert!(rendered.iter().all(|line| line.is_ascii()));
+        assert!(rendered[5]
+            .trim_start()
+            .starts_with("+Dependencies [1/3]---"));
+        assert!(rendered.iter().any(|line| line.contains("^ v: navigate")));
+    }
+
+    #[test]
+    fn renders_fetch_errors_popup() {
+        let buffer = render_with(MANIFEST, &[], &HashMap::new(), |state| {
+            state.fetch_errors = vec![
+                ("react".to_owned(), registry::Error::Empty),
+                ("eslint".to_owned(), registry::Error::TimedOut),
+            ];
+            state.shows_fetch_errors = true;
+        });
+        let lines = lines(&buffer);
+
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("2 packages failed to fetch")));
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("react: empty registry response")));
+        assert!(lines.iter().any(|line| line.contains("eslint: timed out")));
+    }
+
+    #[test]
+    fn does_not_count_missing_optional_peers_as_errors() {
+        let manifest = r#"{
+            "name": "ui-kit",
+            "version": "1.0.0",
+            "peerDependencies": { "react": "^18.2.0", "react-native-web": "^0.19.0" },
+            "peerDependenciesMeta": { "react-native-web": { "optional": true } }
+        }"#;
+        let fetched_packages = HashMap::from([("react".to_owned(), FetchedPackage::at("18.2.0"))]);
+        let missing = |name: &str| vec![(name.to_owned(), registry::Error::NotFound)];
+
+        let buffer = render_with(manifest, &[], &fetched_packages, |state| {
+            state.optional_packages = HashSet::from(["react-native-web".to_owned()]);
+            state.fetch_errors = missing("react-native-web");
+        });
+        let done = lines(&buffer);
+        assert!(done[3].contains("Done"));
+        assert!(!done[3].contains("error") && !done[3].contains("failed"));
+        assert!(done[5].contains(text::ALL_UP_TO_DATE));
+
+        let buffer = render_with(manifest, &[], &fetched_packages, |state| {
+            state.fetch_errors = missing("react-native-web");
+        });
+        assert!(lines(&buffer)[3].contains("Done with 1 error"));
+    }
+
+    #[test]
+    fn renders_optional_peers_differently() {
+        let manifest = r#"{
+            "name": "ui-kit",
+            "version": "1.0.0",
+            "peerDependencies": { "react": "^17.0.0", "react-native": "^0.70.0" },
+            "peerDependenciesMeta": { "react-native": { "optional": true } }
+        }"#;
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+            ("react-native".to_owned(), FetchedPackage::at("0.73.0")),
+        ]);
+        let buffer = render(manifest, &[], &fetched_packages);
+        let lines = lines(&buffer);
+
+        assert!(lines[5].contains("Peer Dependencies [1/2]"));
+        assert!(lines[6].contains("react ") && !lines[6].contains("(optional)"));
+        assert!(lines[7].contains("react-native (optional)"));
+        let required = find(&buffer, "18.2.0");
+        assert_eq!(buffer.get(required.0, required.1).fg, Color::Red);
+        let optional = find(&buffer, "0.73.0");
+        assert_eq!(buffer.get(optional.0, optional.1).fg, Color::DarkGray);
+    }
+
+    #[test]
+    fn renders_fetched_versions_by_severity() {
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.21")),
+            ("typescript".to_owned(), FetchedPackage::at("4.5.5")),
+        ]);
+        let buffer = render(MANIFEST, &[], &fetched_packages);
+        let lines = lines(&buffer);
+
+        assert!(lines[3].contains("3/5"));
+        assert!(lines[2].contains("Runtime") && lines[2].contains("Dev"));
+        let runtime_progress = find(&buffer, "2/3");
+        let dev_progress = find(&buffer, "1/2");
+        assert!(runtime_progress.0 < dev_progress.0 && dev_progress.1 == 3);
+        assert!(lines[5].contains("Dependencies [1/3]"));
+        assert!(lines[6].starts_with("  │react ") && lines[6].contains("^17.0.2"));
+        assert!(lines[7].contains("^4.17.21") && lines[7].contains("up to date"));
+        assert!(lines[8].contains("left-pad") && lines[8].contains("1.3.0"));
+
+        let major = find(&buffer, "18.2.0");
+        assert_eq!(buffer.get(major.0, major.1).fg, Color::Red);
+        let patch = find(&buffer, "4.5.5");
+        assert_eq!(buffer.get(patch.0, patch.1).fg, Color::Green);
+        let up_to_date = find(&buffer, UP_TO_DATE);
+        assert_eq!(buffer.get(up_to_date.0, up_to_date.1).fg, Color::DarkGray);
+
+        let buffer = render_with(MANIFEST, &[], &fetched_packages, |state| {
+            state.severity_colors.major = Color::Magenta;
+        });
+        let major = find(&buffer, "18.2.0");
+        assert_eq!(buffer.get(major.0, major.1).fg, Color::Magenta);
+    }
+
+    #[test]
+    fn renders_visible_columns() {
+        let mut react = FetchedPackage::at("18.2.0");
+        react.wanted = "17.0.2".to_owned();
+        let fetched_packages = HashMap::from([("react".to_owned(), react)]);
+
+        let render_columns = |visible_columns| {
+            let buffer = render_with(MANIFEST, &["--prod-only"], &fetched_packages, |state| {
+                state.visible_columns = visible_columns
+            });
+            lines(&buffer)[6].clone()
+        };
+
+        let current = render_columns(VisibleColumns::Current);
+        assert!(current.contains("^17.0.2") && !current.contains("18.2.0"));
+
+        let current_and_latest = render_columns(VisibleColumns::CurrentAndLatest);
+        assert!(current_and_latest.contains("18.2.0") && !current_and_latest.contains(" 17.0.2"));
+
+        let all = render_columns(VisibleColumns::CurrentWantedAndLatest);
+        let wanted = all.find(" 17.0.2").unwrap();
+        assert!(all.find("^17.0.2").unwrap() < wanted && wanted < all.find("18.2.0").unwrap());
+        assert!(!all.contains(OUT_OF_RANGE));
+
+        let with_range = render_columns(VisibleColumns::CurrentWantedLatestAndRange);
+        assert!(with_range.find("18.2.0").unwrap() < with_range.find(OUT_OF_RANGE).unwrap());
+    }
+
+    #[test]
+    fn renders_confirmation_diff() {
+        let change = |name: &str, kind, from: &str, to: &str| VersionChange {
+            name: name.to_owned(),
+            kind,
+            from: from.to_owned(),
+            to: to.to_owned(),
+            is_flattened: false,
+            pointer: None,
+        };
+        let buffer = render_with(MANIFEST, &[], &HashMap::new(), |state| {
+            state.confirmation = Some(Confirmation {
+                changes: vec![
+                    change("react", DependencyTable::Runtime, "^17.0.2", "^18.2.0"),
+                    change("eslint", DependencyTable::Dev, "^8.0.0", "^9.0.0"),
+                ],
+                after_update: AfterUpdate::Stay,
+            })
+        });
+        let lines = lines(&buffer);
+
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("Update package.json? 2 changes")));
+        let removed = find(&buffer, r#"-     "react": "^17.0.2""#);
+        let added = find(&buffer, r#"+     "react": "^18.2.0""#);
+        assert_eq!(added.1, removed.1 + 1);
+        assert_eq!(buffer.get(removed.0, removed.1).fg, Color::Red);
+        assert_eq!(buffer.get(added.0, added.1).fg, Color::Green);
+        find(&buffer, r#""devDependencies": {"#);
+    }
+
+    #[test]
+    fn scrolls_as_little_as_possible() {
+        assert_eq!(scroll_offset(0, Some(5), 10, 100), 0);
+        assert_eq!(scroll_offset(0, Some(10), 10, 100), 1);
+        assert_eq!(scroll_offset(20, Some(5), 10, 100), 5);
+        // Rows removed by a refresh don't leave the end of the table empty
+        assert_eq!(scroll_offset(95, Some(12), 10, 15), 5);
+        assert_eq!(scroll_offset(3, None, 10, 100), 3);
+        assert_eq!(scroll_offset(3, Some(0), 0, 100), 0);
+    }
+
+    #[test]
+    fn renders_only_rows_in_view() {
+        let dependencies: Vec<String> = (0..600)
+            .map(|i| format!("\"package-{}\": \"^1.0.0\"", i))
+            .collect();
+        let manifest = format!(
+            r#"{{ "name": "large", "version": "1.0.0", "dependencies": {{ {} }} }}"#,
+            dependencies.join(", ")
+        );
+
+        let buffer = render_with(&manifest, &[], &HashMap::new(), |state| {
+            state
+                .table_mut(DependencyTable::Runtime)
+                .table_state
+                .select(Some(500))
+        });
+        let lines = lines(&buffer);
+
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("Dependencies [501/600]")));
+        let selected = find(&buffer, "package-500 ");
+        assert_eq!(buffer.get(selected.0, selected.1).bg, Color::DarkGray);
+        assert!(lines.iter().all(|line| !line.contains("package-0 ")));
+    }
 }
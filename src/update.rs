@@ -0,0 +1,568 @@
+//! Apply updates to `package.json`, either from the interactive UI or all at once with `--update-all`.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::OpenOptions,
+    io::{self, stdin, stdout, Write},
+    path::Path,
+    process::ExitCode,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Error};
+
+use crate::{
+    application::DependencyTable,
+    args::{Args, UnionRanges},
+    dependency::{Dependency, Selection},
+    project::Project,
+    report, text,
+    version::{self, UpdatePolicy},
+};
+
+/// Update every outdated dependency without starting the interactive UI, or only the ones given with `--set`.
+///
+/// `package.json` is left untouched if no declared version changes, so that scripts don't cause needless diffs.
+/// With `--dry-run`, the changes are only printed as a diff.
+pub async fn run(args: Args) -> Result<ExitCode, Error> {
+    let path = args.manifest_path()?;
+    let mut project = Project::new(&path)?;
+    for warning in project.warnings() {
+        eprintln!("⚠ {}", warning);
+    }
+    let update_policy = UpdatePolicy::new(&args, &project, &path)?;
+    let selection = Selection::new(&args, &project)?;
+    let mut changes = set_changes(&project, &selection, &args.set)?;
+
+    // Packages given with --set are written as they are, so there is nothing to fetch for them
+    let selection = selection.without(args.set.iter().map(|(name, _)| name));
+    let fetched_packages = match args.update_all {
+        true => report::fetch_update_targets(&args, &project, &selection, &update_policy).await?,
+        false => HashMap::new(),
+    };
+
+    let mut outdated = report::outdated(&project, &selection, &fetched_packages);
+    outdated.retain(|dependency| selection.updates(dependency.kind));
+    if args.no_bulk_dev {
+        outdated.retain(|dependency| dependency.kind != DependencyTable::Dev);
+    }
+    let outdated: Vec<_> = outdated
+        .into_iter()
+        .filter_map(|dependency| dependency.with_update_up_to(args.update_up_to))
+        .collect();
+    changes.extend(plan(&outdated, args.union_ranges));
+    for dependency in skipped_unions(&outdated, args.union_ranges) {
+        eprintln!(
+            "⚠ {} {}: ranges joined by || are left as they are, see --union-ranges",
+            dependency.name, dependency.declared
+        );
+    }
+
+    if changes.is_empty() {
+        println!("{} is already up to date", args.manifest);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.dry_run {
+        print!("{}", text::diff(&project, &preview(&project, &changes)));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for change in &changes {
+        println!("{}", change);
+    }
+    if let Some(warning) = uncommitted_changes_warning(&args, &project) {
+        eprintln!("⚠ {}", warning);
+    }
+    if !args.yes && !confirm(&format!("Apply {} updates?", changes.len()))? {
+        println!("{} was not changed", args.manifest);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    apply(&mut project, &changes);
+    project.write_to_file()?;
+    if let Some(log_file) = &args.log_file {
+        append_to_log(log_file, &changes)?;
+    }
+    println!(
+        "Updated {} dependencies in {}",
+        changes.len(),
+        args.manifest
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Warning to show before writing `package.json` if it has uncommitted changes, unless `--no-git-check` is given.
+pub fn uncommitted_changes_warning(args: &Args, project: &Project) -> Option<String> {
+    if args.no_git_check || !project.has_uncommitted_changes() {
+        return None;
+    }
+
+    Some(format!(
+        "{} has uncommitted changes; updates will be mixed in",
+        project.file_name()
+    ))
+}
+
+/// Remove the duplicates reported by `Project::warnings` from `package.json`.
+///
+/// A devDependency that is also a runtime dependency is removed, since the runtime one is installed anyway.
+/// Writing the file back keeps only the last of keys that are listed twice, which is the one npm reads.
+pub fn fix_duplicates(args: Args) -> Result<ExitCode, Error> {
+    let path = args.manifest_path()?;
+    let mut project = Project::new(&path)?;
+
+    let duplicate_keys = project.duplicate_keys().to_vec();
+    let removed = project.remove_duplicate_dev_dependencies();
+    if removed.is_empty() && duplicate_keys.is_empty() {
+        println!("{} has no duplicate dependencies", args.manifest);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(warning) = uncommitted_changes_warning(&args, &project) {
+        eprintln!("⚠ {}", warning);
+    }
+    // Only tell what was removed once it is written
+    project.write_to_file()?;
+    for key in duplicate_keys {
+        println!("Removed all but the last {}", key);
+    }
+    for name in removed {
+        println!(
+            "Removed {} from devDependencies, it is already in dependencies",
+            name
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A change of a declared version, displayed like `react ^17.0.2 -> ^18.2.0`.
+pub struct VersionChange {
+    pub name: String,
+    pub kind: DependencyTable,
+    pub from: String,
+    pub to: String,
+    /// Whether the declared range could not be kept, so it was replaced by the target version alone.
+    pub is_flattened: bool,
+    /// JSON pointer of the changed override pin, `None` for declared versions.
+    pub pointer: Option<String>,
+}
+
+impl fmt::Display for VersionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} -> {}", self.name, self.from, self.to)?;
+        if self.is_flattened {
+            f.write_str(" (range replaced by a single version)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Changes that updating `dependencies` would make, keeping the style of their ranges where possible.
+///
+/// Workspace links, dependencies whose declared version would stay the same and unions of ranges that
+/// `union_ranges` says to skip are left out.
+pub fn plan(dependencies: &[Dependency], union_ranges: UnionRanges) -> Vec<VersionChange> {
+    let mut changes = Vec::new();
+
+    for dependency in dependencies {
+        if dependency.is_workspace_link() {
+            continue;
+        }
+        let latest_version = match dependency.latest {
+            Some(latest_version) => latest_version,
+            None => continue,
+        };
+        if version::is_union(dependency.declared) {
+            if let Some(updated_union) =
+                version::updated_union(dependency.declared, latest_version, union_ranges)
+            {
+                changes.push(VersionChange {
+                    name: dependency.name.to_owned(),
+                    kind: dependency.kind,
+                    from: dependency.declared.to_owned(),
+                    to: updated_union,
+                    is_flattened: false,
+                    pointer: dependency.pointer.clone(),
+                });
+            }
+            continue;
+        }
+        let updated_range = version::updated_range(dependency.declared, latest_version);
+        let is_flattened = updated_range.is_none();
+        let updated_version = updated_range.unwrap_or_else(|| latest_version.to_owned());
+
+        if updated_version != dependency.declared {
+            changes.push(VersionChange {
+                name: dependency.name.to_owned(),
+                kind: dependency.kind,
+                from: dependency.declared.to_owned(),
+                to: updated_version,
+                is_flattened,
+                pointer: dependency.pointer.clone(),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Changes that write the exact versions given with `--set`, wherever the packages are declared.
+///
+/// Overrides are only changed along with the declared versions, like for fetched updates. Peer ranges are left as
+/// they are, since an exact version would reject every other version of the host package.
+pub fn set_changes(
+    project: &Project,
+    selection: &Selection,
+    versions: &[(String, String)],
+) -> Result<Vec<VersionChange>, Error> {
+    let mut changes = Vec::new();
+    for (name, version) in versions {
+        let declarations: Vec<_> = selection
+            .tables
+            .iter()
+            .filter(|&&table| !matches!(table, DependencyTable::Peer | DependencyTable::Overrides))
+            .flat_map(|&table| {
+                selection
+                    .dependencies(project, table)
+                    .filter(|(declared_name, _)| *declared_name == name)
+                    .map(move |(_, declared)| (table, declared.as_str().unwrap_or_default()))
+            })
+            .collect();
+        let is_peer = selection
+            .dependencies(project, DependencyTable::Peer)
+            .any(|(declared_name, _)| declared_name == name);
+        match declarations.is_empty() {
+            true if is_peer => bail!(
+                "{} is only a peer dependency, whose range --set leaves as it is",
+                name
+            ),
+            true => bail!("{} is not a dependency in package.json", name),
+            false => {}
+        }
+
+        changes.extend(
+            declarations
+                .into_iter()
+                .filter(|(_, declared)| declared != version)
+                .map(|(kind, declared)| VersionChange {
+                    name: name.clone(),
+                    kind,
+                    from: declared.to_owned(),
+                    to: version.clone(),
+                    is_flattened: false,
+                    pointer: None,
+                }),
+        );
+    }
+
+    Ok(changes)
+}
+
+/// Dependencies with an update that `plan` leaves out because they declare a union of ranges.
+pub fn skipped_unions<'a>(
+    dependencies: &'a [Dependency<'a>],
+    union_ranges: UnionRanges,
+) -> impl Iterator<Item = &'a Dependency<'a>> {
+    dependencies.iter().filter(move |dependency| {
+        union_ranges == UnionRanges::Skip
+            && version::is_union(dependency.declared)
+            && dependency.latest.is_some()
+    })
+}
+
+/// Write the `changes` into the declared versions of `project`, and into overrides that pin the same packages.
+///
+/// Changes of the `Overrides` table only rewrite the pins they were planned from.
+pub fn apply(project: &mut Project, changes: &[VersionChange]) {
+    for change in changes {
+        match change.kind {
+            DependencyTable::Overrides => {
+                if let Some(pointer) = &change.pointer {
+                    project.update_override(pointer, &change.to);
+                }
+            }
+            _ => {
+                project.update_dependency_version(change.kind.key(), &change.name, &change.to);
+                project.sync_overrides(&change.name, &change.to);
+            }
+        }
+    }
+}
+
+/// The project as it would be written after applying the `changes`, e.g. to show what they change.
+pub fn preview(project: &Project, changes: &[VersionChange]) -> Project {
+    let mut updated_project = project.clone();
+    apply(&mut updated_project, changes);
+
+    updated_project
+}
+
+/// Append timestamped lines describing applied updates, e.g. `2024-01-01T12:00:00Z react ^17.0.2 -> ^18.2.0`.
+pub fn append_to_log(path: &Path, changes: &[VersionChange]) -> io::Result<()> {
+    let mut log_file = OpenOptions::new().create(true).append(true).open(path)?;
+    let timestamp = humantime::format_rfc3339_seconds(SystemTime::now());
+
+    for change in changes {
+        writeln!(log_file, "{} {}", timestamp, change)?;
+    }
+
+    Ok(())
+}
+
+/// Ask a yes/no question on the terminal, defaulting to no.
+fn confirm(question: &str) -> io::Result<bool> {
+    print!("{} [y/N] ", question);
+    stdout().flush()?;
+
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        application::{DependencyTable, SortMode},
+        dependency::FetchedPackage,
+    };
+
+    #[test]
+    fn leaves_workspace_links_untouched() {
+        let mut project = Project::parse(
+            r#"{
+                "dependencies": { "@monorepo/shared": "workspace:*", "@monorepo/ui": "workspace:^", "react": "^17.0.2" }
+            }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        assert_eq!(selection.names(&project).collect::<Vec<_>>(), ["react"]);
+
+        // A package of the same name may exist in the registry, but must not replace the link
+        let fetched_packages = HashMap::from([
+            ("@monorepo/shared".to_owned(), FetchedPackage::at("2.0.0")),
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+        ]);
+        let listed_project = project.clone();
+        let dependencies = Dependency::list(
+            &listed_project,
+            &selection,
+            &fetched_packages,
+            DependencyTable::Runtime,
+            SortMode::Manifest,
+            &HashSet::new(),
+        );
+
+        let changes = plan(&dependencies, UnionRanges::Skip);
+        assert_eq!(
+            changes
+                .iter()
+                .map(VersionChange::to_string)
+                .collect::<Vec<_>>(),
+            ["react ^17.0.2 -> ^18.2.0"]
+        );
+
+        apply(&mut project, &changes);
+        let dependencies = &project.values["dependencies"];
+        assert_eq!(dependencies["@monorepo/shared"], "workspace:*");
+        assert_eq!(dependencies["@monorepo/ui"], "workspace:^");
+    }
+
+    #[test]
+    fn dry_run_shows_what_is_written() {
+        let project = Project::parse(
+            r#"{
+                "dependencies": { "react": "^17.0.2", "lodash": ">=3.0.0 <4.0.0", "left-pad": "1.x" },
+                "devDependencies": { "eslint": "~8.0.0" },
+                "overrides": { "react": "17.0.2", "eslint": { "chalk": "4.0.0" } }
+            }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.21")),
+            ("left-pad".to_owned(), FetchedPackage::at("1.3.0")),
+            ("eslint".to_owned(), FetchedPackage::at("8.57.0")),
+        ]);
+        let outdated = report::outdated(&project, &selection, &fetched_packages);
+        let changes = plan(&outdated, UnionRanges::Skip);
+
+        let dry_run = text::diff(&project, &preview(&project, &changes));
+        assert_eq!(
+            dry_run,
+            [
+                r#"  "dependencies": {"#,
+                r#"-     "react": "^17.0.2""#,
+                r#"+     "react": "^18.2.0""#,
+                r#"-     "lodash": ">=3.0.0 <4.0.0""#,
+                r#"+     "lodash": ">=4.17.21 <5.0.0""#,
+                r#"-     "left-pad": "1.x""#,
+                r#"+     "left-pad": "1.3.0""#,
+                "  }",
+                r#"  "devDependencies": {"#,
+                r#"-     "eslint": "~8.0.0""#,
+                r#"+     "eslint": "~8.57.0""#,
+                "  }",
+                r#"  "overrides": {"#,
+                r#"-     "react": "17.0.2""#,
+                r#"+     "react": "^18.2.0""#,
+                "  }",
+                "",
+            ]
+            .join("\n")
+        );
+
+        let mut written = project.clone();
+        apply(&mut written, &changes);
+        let written = Project::parse(&written.to_json_string().unwrap()).unwrap();
+        assert_eq!(dry_run, text::diff(&project, &written));
+    }
+
+    #[test]
+    fn sets_exact_versions_wherever_declared() {
+        let mut project = Project::parse(
+            r#"{
+                "dependencies": { "react": "^17.0.2", "lodash": "^4.17.21" },
+                "peerDependencies": { "react": ">=17" },
+                "overrides": { "react": "17.0.2" }
+            }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from([
+            "packrat",
+            "--set",
+            "react=18.2.0",
+            "--set",
+            "lodash=^4.17.21",
+        ])
+        .unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+
+        let changes = set_changes(&project, &selection, &args.set).unwrap();
+        assert_eq!(
+            changes
+                .iter()
+                .map(VersionChange::to_string)
+                .collect::<Vec<_>>(),
+            ["react ^17.0.2 -> 18.2.0"]
+        );
+        assert!(selection
+            .without(args.set.iter().map(|(name, _)| name))
+            .names(&project)
+            .next()
+            .is_none());
+
+        apply(&mut project, &changes);
+        assert_eq!(project.values["peerDependencies"]["react"], ">=17");
+        assert_eq!(project.values["overrides"]["react"], "18.2.0");
+
+        let unknown = [("left-pad".to_owned(), "1.3.0".to_owned())];
+        assert!(set_changes(&project, &selection, &unknown).is_err());
+        let peer_only = Project::parse(r#"{ "peerDependencies": { "react": ">=17" } }"#).unwrap();
+        let selection = Selection::new(&args, &peer_only).unwrap();
+        let error = set_changes(&peer_only, &selection, &args.set[..1])
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("react is only a peer dependency"));
+    }
+
+    #[test]
+    fn never_writes_dev_dependencies_with_prod_only() {
+        let mut project = Project::parse(
+            r#"{
+                "dependencies": { "react": "^17.0.2", "lodash": "^4.17.0" },
+                "devDependencies": { "eslint": "^8.0.0", "lodash": "^4.17.0" }
+            }"#,
+        )
+        .unwrap();
+        let args = Args::try_parse_from(["packrat", "--prod-only"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+
+        let fetched_packages = HashMap::from([
+            ("react".to_owned(), FetchedPackage::at("18.2.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.21")),
+            ("eslint".to_owned(), FetchedPackage::at("9.0.0")),
+        ]);
+        let listed_project = project.clone();
+        let outdated = report::outdated(&listed_project, &selection, &fetched_packages);
+        apply(&mut project, &plan(&outdated, UnionRanges::Skip));
+
+        assert_eq!(project.values["dependencies"]["lodash"], "^4.17.21");
+        let dev_dependencies = &project.values["devDependencies"];
+        assert_eq!(dev_dependencies["eslint"], "^8.0.0");
+        assert_eq!(dev_dependencies["lodash"], "^4.17.0");
+    }
+
+    #[test]
+    fn updates_only_the_marked_override_pins_with_overrides() {
+        let overrides_project = || {
+            Project::parse(
+                r#"{
+                    "devDependencies": { "eslint": "^8.57.0", "jest": "^29.7.0" },
+                    "overrides": { "chalk": "2.4.2", "eslint": { "lodash": "4.17.0" }, "jest": { "lodash": "4.17.0" } }
+                }"#,
+            )
+            .unwrap()
+        };
+        let fetched_packages = HashMap::from([
+            ("eslint".to_owned(), FetchedPackage::at("8.57.0")),
+            ("jest".to_owned(), FetchedPackage::at("29.7.0")),
+            ("chalk".to_owned(), FetchedPackage::at("5.3.0")),
+            ("lodash".to_owned(), FetchedPackage::at("4.17.21")),
+        ]);
+
+        // Overrides are deliberate pins, so they are not even checked by default
+        let project = overrides_project();
+        let args = Args::try_parse_from(["packrat"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        assert!(report::outdated(&project, &selection, &fetched_packages).is_empty());
+
+        let mut project = overrides_project();
+        let args = Args::try_parse_from(["packrat", "--overrides"]).unwrap();
+        let selection = Selection::new(&args, &project).unwrap();
+        let listed_project = project.clone();
+        let marked = HashSet::from(["/overrides/eslint/lodash".to_owned()]);
+        let dependencies = Dependency::list(
+            &listed_project,
+            &selection,
+            &fetched_packages,
+            DependencyTable::Overrides,
+            SortMode::Manifest,
+            &marked,
+        );
+        assert_eq!(dependencies.len(), 3);
+        let marked: Vec<_> = dependencies
+            .into_iter()
+            .filter(|dependency| dependency.marked)
+            .collect();
+
+        let changes = plan(&marked, UnionRanges::Skip);
+        assert_eq!(
+            changes
+                .iter()
+                .map(VersionChange::to_string)
+                .collect::<Vec<_>>(),
+            ["lodash 4.17.0 -> 4.17.21"]
+        );
+
+        apply(&mut project, &changes);
+        let overrides = &project.values["overrides"];
+        assert_eq!(overrides["eslint"]["lodash"], "4.17.21");
+        assert_eq!(overrides["jest"]["lodash"], "4.17.0");
+        assert_eq!(overrides["chalk"], "2.4.2");
+    }
+}
@@ -0,0 +1,670 @@
+//! Compare declared dependency versions with the versions published to the registry.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use anyhow::Error;
+use clap::ArgEnum;
+use node_semver::{Range, Version};
+
+use crate::{
+    args::{Args, UnionRanges},
+    config::Config,
+    project::Project,
+    registry::Metadata,
+};
+
+/// The most significant part of a version that changes when updating to a newer version.
+///
+/// Variants are ordered from the most to the least severe change.
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VersionSection {
+    Major,
+    /// Any change to a `0.x` version, which semver allows to be breaking.
+    #[clap(name = "pre-1.0")]
+    PreV1,
+    Minor,
+    Patch,
+}
+
+impl fmt::Display for VersionSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            VersionSection::Major => "major",
+            VersionSection::PreV1 => "pre-1.0",
+            VersionSection::Minor => "minor",
+            VersionSection::Patch => "patch",
+        };
+
+        f.write_str(label)
+    }
+}
+
+/// Lowest version allowed by a declared version range, e.g. `1.2.0` for `^1.2.0`.
+pub fn current_version(declared: &str) -> Option<Version> {
+    Range::parse(normalize(declared)).ok()?.min_version()
+}
+
+/// Whether the declared range allows `version`, so that `npm update` installs it without editing `package.json`.
+///
+/// Returns `None` if either cannot be parsed, e.g. for a git URL.
+pub fn allows(declared: &str, version: &str) -> Option<bool> {
+    let range = Range::parse(normalize(declared)).ok()?;
+    let version = Version::parse(version).ok()?;
+
+    Some(range.satisfies(&version))
+}
+
+/// Whether the declared version links a package of the same workspace, e.g. `workspace:*` or `workspace:^`.
+///
+/// Such dependencies are never fetched from the registry or updated.
+pub fn is_workspace_link(declared: &str) -> bool {
+    declared.trim_start().starts_with("workspace:")
+}
+
+/// Whether the declared version is a dist-tag like `beta` or `next` instead of a range, which npm resolves to the
+/// version that the package tags with it.
+pub fn is_dist_tag(declared: &str) -> bool {
+    let declared = declared.trim();
+
+    declared.starts_with(|c: char| c.is_ascii_alphabetic())
+        && declared
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+        && Range::parse(normalize(declared)).is_err()
+}
+
+/// Declared version without surrounding whitespace and a `v` in front of the version, e.g. `^1.2.3` for ` ^v1.2.3`.
+pub fn normalize(declared: &str) -> String {
+    let declared = declared.trim();
+    let (operator, version) = split_operator(declared);
+
+    match version.strip_prefix(['v', 'V']) {
+        Some(version) => format!("{}{}", operator, version),
+        None => declared.to_owned(),
+    }
+}
+
+/// Characters that make up the operator of a single-version range, e.g. `>=` or `^`.
+const RANGE_OPERATORS: [char; 5] = ['^', '~', '=', '<', '>'];
+
+/// Range operator of a declared version that is kept when updating, e.g. `^` in `^1.2.0`.
+pub fn range_prefix(declared: &str) -> Option<char> {
+    match normalize(declared).chars().next() {
+        Some(prefix @ ('~' | '^' | '=')) => Some(prefix),
+        _ => None,
+    }
+}
+
+/// Declared version updated to the `target` version in the same style, e.g. `^1.5.0` for `^1.2.0`.
+///
+/// Bounded ranges keep their upper bound, e.g. `>=1.2.0 <2.0.0` becomes `>=1.5.0 <2.0.0`. If the target is beyond a
+/// bound at a major version, the bound moves to the major after the target, e.g. to `>=3.1.0 <4.0.0`.
+///
+/// Returns `None` for ranges that cannot be rewritten like this, e.g. `1.x` or `^1.0.0 || ^2.0.0`.
+pub fn updated_range(declared: &str, target: &str) -> Option<String> {
+    let target_version = Version::parse(target).ok()?;
+    let declared = normalize(declared);
+    let comparators: Vec<&str> = declared.split_whitespace().collect();
+
+    match comparators.as_slice() {
+        [single] => match (split_operator(single), range_prefix(single)) {
+            ((">=" | ">", lower), _) if is_range(lower) => Some(format!(">={}", target)),
+            ((_, version), Some(prefix)) if is_range(version) => {
+                Some(format!("{}{}", prefix, target))
+            }
+            (("", version), None) if Version::parse(version).is_ok() => Some(target.to_owned()),
+            _ => None,
+        },
+        // The upper version of a hyphen range is included
+        [lower, "-", upper] if is_range(lower) && is_range(&format!("<={}", upper)) => {
+            let range = Range::parse(format!("<={}", upper)).ok()?;
+            range
+                .satisfies(&target_version)
+                .then(|| format!("{} - {}", target, upper))
+        }
+        [lower, upper] => {
+            match split_operator(lower) {
+                (">=" | ">", lower) if is_range(lower) => {}
+                _ => return None,
+            }
+            let upper = match split_operator(upper) {
+                ("<" | "<=", _) if Range::parse(upper).ok()?.satisfies(&target_version) => {
+                    upper.to_string()
+                }
+                ("<", version) => format!("<{}", next_major_bound(version, &target_version)?),
+                _ => return None,
+            };
+            Some(format!(">={} {}", target, upper))
+        }
+        _ => None,
+    }
+}
+
+/// Operator and version of a single comparator, e.g. `>=` and `1.2.0` for `>=1.2.0`.
+fn split_operator(comparator: &str) -> (&str, &str) {
+    let version_start = comparator
+        .find(|c: char| !RANGE_OPERATORS.contains(&c))
+        .unwrap_or(comparator.len());
+
+    comparator.split_at(version_start)
+}
+
+fn is_range(range: &str) -> bool {
+    Range::parse(range).is_ok()
+}
+
+/// Whether the declared range allows several ranges joined by `||`, e.g. `^16 || ^17`.
+pub fn is_union(declared: &str) -> bool {
+    declared.contains("||")
+}
+
+/// Range that a union of ranges should be updated to for the `target`, or `None` to leave it as it is.
+pub fn updated_union(declared: &str, target: &str, union_ranges: UnionRanges) -> Option<String> {
+    match union_ranges {
+        UnionRanges::Skip => None,
+        UnionRanges::Replace => Some(format!("^{}", target)),
+        // A target that one of the ranges already allows needs no new range
+        UnionRanges::Extend => match Version::parse(target) {
+            Ok(version) if is_satisfied(declared, &version) => None,
+            _ => Some(format!("{} || ^{}", declared, target)),
+        },
+    }
+}
+
+fn is_satisfied(declared: &str, version: &Version) -> bool {
+    Range::parse(normalize(declared)).is_ok_and(|range| range.satisfies(version))
+}
+
+/// Major version after the `target` in the format of a major version `bound`, e.g. `4.0.0` for `2.0.0` and `3.1.0`.
+///
+/// Returns `None` if the bound is not at a major version, since it's then unclear how far the range should reach.
+fn next_major_bound(bound: &str, target: &Version) -> Option<String> {
+    let mut parts: Vec<&str> = bound.split('.').collect();
+    if parts[0].parse::<u64>().is_err() || parts[1..].iter().any(|part| *part != "0") {
+        return None;
+    }
+
+    let next_major = (target.major + 1).to_string();
+    parts[0] = &next_major;
+    Some(parts.join("."))
+}
+
+/// Classify the update from the `current` version to the `latest` version.
+///
+/// Returns `None` if the latest version is not newer than the current one or cannot be parsed.
+pub fn classify(current: &Version, latest: &str) -> Option<VersionSection> {
+    let latest = Version::parse(latest).ok()?;
+
+    if latest <= *current {
+        return None;
+    }
+
+    let section = if latest.major != current.major {
+        VersionSection::Major
+    } else if current.major == 0 {
+        VersionSection::PreV1
+    } else if latest.minor != current.minor {
+        VersionSection::Minor
+    } else {
+        VersionSection::Patch
+    };
+
+    Some(section)
+}
+
+/// Whether the `current` version is the `latest` version or a newer one.
+///
+/// Returns `false` if the latest version cannot be parsed, since it's then unknown whether an update is available.
+pub fn is_up_to_date(current: &Version, latest: &str) -> bool {
+    Version::parse(latest).is_ok_and(|latest| latest <= *current)
+}
+
+/// Whether any published version satisfies the declared range, i.e. whether it can still be installed.
+///
+/// Returns `true` if the registry didn't list versions or the declared version is not a range, e.g. a git URL.
+/// A latest version missing from the listed ones counts as published, since a mirror lists it later.
+pub fn is_published(metadata: &Metadata, declared: &str) -> bool {
+    let range = match Range::parse(normalize(declared)) {
+        Ok(range) => range,
+        Err(_) => return true,
+    };
+
+    metadata.versions.is_empty()
+        || metadata
+            .versions
+            .keys()
+            .map(String::as_str)
+            .chain(metadata.unlisted_latest())
+            .filter_map(|version| Version::parse(version).ok())
+            .any(|version| satisfies(&range, &version, true))
+}
+
+/// Rules for choosing the version that a dependency should be updated to.
+#[derive(Default)]
+pub struct UpdatePolicy {
+    /// Only consider versions that satisfy the declared range.
+    pub in_range: bool,
+    /// Consider prerelease versions even if the current version is not a prerelease.
+    pub include_prerelease: bool,
+    /// Node version supported by the project. Versions requiring a newer Node are skipped.
+    pub node_version: Option<Version>,
+    /// Dist-tag to update to instead of `latest`, from `--tag`.
+    pub tag: Option<String>,
+    /// Dist-tags of single packages, which take precedence over `tag`.
+    pub package_tags: HashMap<String, String>,
+    /// Number of stable releases to stay behind the newest version, from `--lag`.
+    pub lag: usize,
+}
+
+impl UpdatePolicy {
+    pub fn new(args: &Args, project: &Project, manifest_path: &Path) -> Result<Self, Error> {
+        let directory = manifest_path
+            .parent()
+            .expect("package.json path has a parent directory");
+        let mut policy = UpdatePolicy {
+            in_range: args.in_range,
+            include_prerelease: args.include_prerelease,
+            tag: args.tag.clone(),
+            package_tags: Config::load(directory)?.tags,
+            lag: args.lag,
+            ..Default::default()
+        };
+        if args.respect_engines {
+            policy.node_version = project.node_version(directory);
+        }
+
+        Ok(policy)
+    }
+
+    /// Version that the package is tagged with, falling back to `latest` if it has no such tag.
+    fn tagged_version<'a>(&self, metadata: &'a Metadata) -> (&'a str, bool) {
+        let tag = self
+            .package_tags
+            .get(&metadata.name)
+            .or(self.tag.as_ref())
+            .filter(|tag| *tag != "latest");
+
+        match tag.and_then(|tag| metadata.dist_tags.get(tag)) {
+            Some(version) => (version, true),
+            None => (&metadata.dist_tags.latest, false),
+        }
+    }
+
+    /// Version that a dependency declared with the `declared` range should be updated to.
+    pub fn update_target(&self, metadata: &Metadata, declared: &str) -> String {
+        let (latest, is_tagged) = self.tagged_version(metadata);
+        let include_prerelease = self.include_prerelease
+            || current_version(declared).is_some_and(|current| current.is_prerelease());
+
+        let target = if self.in_range {
+            let range = match Range::parse(declared) {
+                Ok(range) => range,
+                Err(_) => return latest.to_owned(),
+            };
+
+            self.candidates(metadata, include_prerelease)
+                .filter(|version| satisfies(&range, version, include_prerelease))
+                .max()
+                .or_else(|| range.min_version())
+        } else {
+            match Version::parse(latest) {
+                // Prereleases can be newer than the version tagged as latest
+                Ok(latest) if include_prerelease => self
+                    .candidates(metadata, include_prerelease)
+                    .chain([latest])
+                    .max(),
+                // A tag other than latest is chosen explicitly, even if it points at a prerelease
+                Ok(latest) if !latest.is_prerelease() || is_tagged => Some(latest),
+                // Some packages tag a prerelease as latest, use the newest stable version instead
+                _ => self.candidates(metadata, include_prerelease).max(),
+            }
+            .map(|target| self.lag_behind(metadata, target, declared))
+        };
+
+        match target {
+            Some(target) => target.to_string(),
+            None => latest.to_owned(),
+        }
+    }
+
+    /// Newest version allowed by the declared range, like the "wanted" version of `npm outdated`.
+    pub fn wanted(&self, metadata: &Metadata, declared: &str) -> String {
+        let in_range_policy = UpdatePolicy {
+            in_range: true,
+            include_prerelease: self.include_prerelease,
+            node_version: self.node_version.clone(),
+            ..Default::default()
+        };
+
+        in_range_policy.update_target(metadata, declared)
+    }
+
+    /// Newest patch, minor and major update of the current version, plus the update target, in ascending order.
+    ///
+    /// These are the targets that can be chosen for a single package instead of the default one.
+    pub fn level_targets(&self, metadata: &Metadata, declared: &str) -> Vec<String> {
+        let current = match current_version(declared) {
+            Some(current) => current,
+            None => return Vec::new(),
+        };
+        let include_prerelease = self.include_prerelease || current.is_prerelease();
+        let newer: Vec<Version> = self
+            .candidates(metadata, include_prerelease)
+            .filter(|version| *version > current)
+            .collect();
+
+        let newest_patch = newer
+            .iter()
+            .filter(|v| v.major == current.major && v.minor == current.minor)
+            .max();
+        let newest_minor = newer.iter().filter(|v| v.major == current.major).max();
+        let newest_major = newer.iter().max();
+        let target = Version::parse(self.update_target(metadata, declared)).ok();
+
+        let mut targets: Vec<Version> = [newest_patch, newest_minor, newest_major]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .chain(target.filter(|target| *target > current))
+            .collect();
+        targets.sort();
+        targets.dedup();
+
+        targets.iter().map(Version::to_string).collect()
+    }
+
+    /// Stable version `lag` releases older than `target`, without going below the current version.
+    fn lag_behind(&self, metadata: &Metadata, target: Version, declared: &str) -> Version {
+        if self.lag == 0 {
+            return target;
+        }
+
+        let mut older: Vec<Version> = self
+            .candidates(metadata, false)
+            .filter(|version| *version < target)
+            .collect();
+        older.sort_unstable_by(|a, b| b.cmp(a));
+        let lagged = older.into_iter().nth(self.lag - 1);
+
+        lagged
+            .into_iter()
+            .chain(current_version(declared))
+            .max()
+            .unwrap_or(target)
+    }
+
+    /// Published versions that are allowed as an update target regardless of the declared range.
+    fn candidates<'a>(
+        &'a self,
+        metadata: &'a Metadata,
+        include_prerelease: bool,
+    ) -> impl Iterator<Item = Version> + 'a {
+        // The tag is trusted over the list, the engines of the unlisted version are unknown like for old releases
+        let unlisted_latest = metadata
+            .unlisted_latest()
+            .and_then(|latest| Version::parse(latest).ok());
+
+        metadata
+            .versions
+            .iter()
+            .filter(|(_, version_metadata)| match &self.node_version {
+                Some(node_version) => version_metadata
+                    .node_engine()
+                    .and_then(|engine| Range::parse(engine).ok())
+                    .is_none_or(|engine| engine.satisfies(node_version)),
+                None => true,
+            })
+            .filter_map(|(version, _)| Version::parse(version).ok())
+            .chain(unlisted_latest)
+            .filter(move |version| include_prerelease || !version.is_prerelease())
+    }
+}
+
+/// Whether the version satisfies the range, optionally allowing prereleases of any version within the range.
+///
+/// By default, a range only allows prereleases of the exact version that it starts from.
+fn satisfies(range: &Range, version: &Version, include_prerelease: bool) -> bool {
+    if range.satisfies(version) {
+        return true;
+    }
+    if !include_prerelease || !version.is_prerelease() {
+        return false;
+    }
+
+    let release = Version::new(version.major, version.minor, version.patch);
+    range.satisfies(&release) && range.min_version().is_some_and(|min| *version >= min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_sloppy_versions() {
+        assert_eq!(normalize(" ^1.0.0"), "^1.0.0");
+        assert_eq!(normalize("v1.0.0"), "1.0.0");
+        assert_eq!(normalize("=1.0.0"), "=1.0.0");
+        assert_eq!(normalize("~v1.0.0 "), "~1.0.0");
+    }
+
+    #[test]
+    fn recognizes_dist_tags() {
+        for tag in ["beta", "next", "latest", " canary ", "rc-1.x"] {
+            assert!(is_dist_tag(tag), "{}", tag);
+        }
+        for range in [
+            "x",
+            "*",
+            "^1.0.0",
+            "v1.2.3",
+            "",
+            "workspace:*",
+            "npm:react@18",
+        ] {
+            assert!(!is_dist_tag(range), "{}", range);
+        }
+    }
+
+    #[test]
+    fn keeps_range_operator_of_sloppy_versions() {
+        assert_eq!(range_prefix(" ^1.0.0"), Some('^'));
+        assert_eq!(range_prefix("v1.0.0"), None);
+        assert_eq!(range_prefix("=1.0.0"), Some('='));
+    }
+
+    #[test]
+    fn keeps_style_of_declared_range() {
+        assert_eq!(updated_range("^1.2.0", "1.5.0").unwrap(), "^1.5.0");
+        assert_eq!(updated_range(" ~v1.2.0", "1.5.0").unwrap(), "~1.5.0");
+        assert_eq!(updated_range("1.2.0", "1.5.0").unwrap(), "1.5.0");
+        assert_eq!(updated_range(">=1.2.0", "1.5.0").unwrap(), ">=1.5.0");
+    }
+
+    #[test]
+    fn keeps_upper_bound_of_bounded_ranges() {
+        assert_eq!(
+            updated_range(">=1.2.0 <2.0.0", "1.5.0").unwrap(),
+            ">=1.5.0 <2.0.0"
+        );
+        assert_eq!(
+            updated_range(">1.2.0 <=1.9.0", "1.5.0").unwrap(),
+            ">=1.5.0 <=1.9.0"
+        );
+        assert_eq!(
+            updated_range("1.2.0 - 1.9.0", "1.5.0").unwrap(),
+            "1.5.0 - 1.9.0"
+        );
+        assert_eq!(updated_range(">=1.2 <2", "3.1.0").unwrap(), ">=3.1.0 <4");
+        assert_eq!(
+            updated_range(">=1.2.0 <2.0.0", "3.1.0").unwrap(),
+            ">=3.1.0 <4.0.0"
+        );
+    }
+
+    #[test]
+    fn does_not_rewrite_exotic_ranges() {
+        for declared in [
+            "1.x",
+            "*",
+            "^1.0.0 || ^2.0.0",
+            ">=1.2.0 <1.4.0",
+            "1.2.0 - 1.4.0",
+            "<2.0.0",
+        ] {
+            assert_eq!(updated_range(declared, "1.5.0"), None, "{}", declared);
+        }
+    }
+
+    #[test]
+    fn updates_unions_of_ranges_as_configured() {
+        assert!(is_union("^16 || ^17"));
+        assert!(!is_union("^17.0.2"));
+        // The union must not be mistaken for a caret range
+        assert_eq!(updated_range("^16 || ^17", "18.2.0"), None);
+
+        assert_eq!(
+            updated_union("^16 || ^17", "18.2.0", UnionRanges::Skip),
+            None
+        );
+        assert_eq!(
+            updated_union("^16 || ^17", "18.2.0", UnionRanges::Replace).as_deref(),
+            Some("^18.2.0")
+        );
+        assert_eq!(
+            updated_union("^16 || ^17", "18.2.0", UnionRanges::Extend).as_deref(),
+            Some("^16 || ^17 || ^18.2.0")
+        );
+        assert_eq!(
+            updated_union("^16 || ^17", "17.0.2", UnionRanges::Extend),
+            None
+        );
+    }
+
+    fn metadata(versions: &[&str]) -> Metadata {
+        serde_json::from_value(serde_json::json!({
+            "name": "left-pad",
+            "dist-tags": { "latest": "1.3.0" },
+            "versions": versions.iter().map(|v| (v.to_string(), serde_json::json!({}))).collect::<serde_json::Map<_, _>>(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn tells_whether_ranges_allow_versions() {
+        assert_eq!(allows("^17.0.2", "17.1.0"), Some(true));
+        assert_eq!(allows("~4.5.2", "4.6.0"), Some(false));
+        assert_eq!(allows(">=1.0.0 <3", "2.9.9"), Some(true));
+        assert_eq!(allows("github:stevemao/left-pad", "1.3.0"), None);
+    }
+
+    #[test]
+    fn ignores_build_metadata() {
+        let current = current_version("1.0.0+a").unwrap();
+        assert_eq!(current, Version::parse("1.0.0").unwrap());
+        assert_eq!(classify(&current, "1.0.0+b"), None);
+        assert!(is_up_to_date(&current, "1.0.0+b"));
+        assert_eq!(classify(&current, "1.0.1+a"), Some(VersionSection::Patch));
+
+        let mut metadata = metadata(&["1.0.0+a", "1.0.0+b"]);
+        metadata.dist_tags.latest = "1.0.0+b".to_owned();
+        let policy = UpdatePolicy::default();
+        assert!(policy.level_targets(&metadata, "1.0.0+a").is_empty());
+        let target = policy.update_target(&metadata, "^1.0.0+a");
+        assert_eq!(classify(&current, &target), None);
+    }
+
+    #[test]
+    fn detects_unpublished_current_version() {
+        let metadata = metadata(&["1.1.0", "1.3.0"]);
+        assert!(is_published(&metadata, "1.3.0"));
+        assert!(is_published(&metadata, "^1.2.0"));
+        assert!(!is_published(&metadata, "1.2.0"));
+        assert!(!is_published(&metadata, "^2.0.0"));
+        assert!(is_published(&metadata, "github:stevemao/left-pad"));
+    }
+
+    #[test]
+    fn offers_newest_patch_minor_and_major_targets() {
+        let mut metadata = metadata(&["1.0.0", "1.0.3", "1.2.0", "1.4.1", "2.0.0", "3.1.0-beta.1"]);
+        metadata.dist_tags.latest = "2.0.0".to_owned();
+        let policy = UpdatePolicy::default();
+
+        assert_eq!(
+            policy.level_targets(&metadata, "^1.0.0"),
+            ["1.0.3", "1.4.1", "2.0.0"]
+        );
+        assert_eq!(
+            policy.level_targets(&metadata, "^1.4.0"),
+            ["1.4.1", "2.0.0"]
+        );
+        assert!(policy.level_targets(&metadata, "^2.0.0").is_empty());
+    }
+
+    #[test]
+    fn updates_to_tagged_versions() {
+        let mut metadata = metadata(&["1.3.0", "2.0.0-rc.1", "2.0.0-rc.2"]);
+        metadata
+            .dist_tags
+            .other
+            .insert("next".to_owned(), "2.0.0-rc.1".to_owned());
+        let mut policy = UpdatePolicy::default();
+        assert_eq!(policy.update_target(&metadata, "^1.0.0"), "1.3.0");
+
+        policy.tag = Some("next".to_owned());
+        assert_eq!(policy.update_target(&metadata, "^1.0.0"), "2.0.0-rc.1");
+
+        // Packages without the tag fall back to latest, and per-package tags take precedence
+        policy.tag = Some("beta".to_owned());
+        assert_eq!(policy.update_target(&metadata, "^1.0.0"), "1.3.0");
+        policy.package_tags = HashMap::from([("left-pad".to_owned(), "next".to_owned())]);
+        assert_eq!(policy.update_target(&metadata, "^1.0.0"), "2.0.0-rc.1");
+    }
+
+    #[test]
+    fn lags_behind_the_latest_version() {
+        let metadata = metadata(&["1.0.0", "1.1.0", "1.2.0-beta.1", "1.2.0", "1.3.0"]);
+        let policy = |lag| UpdatePolicy {
+            lag,
+            ..Default::default()
+        };
+
+        assert_eq!(policy(0).update_target(&metadata, "^1.0.0"), "1.3.0");
+        assert_eq!(policy(1).update_target(&metadata, "^1.0.0"), "1.2.0");
+        assert_eq!(policy(2).update_target(&metadata, "^1.0.0"), "1.1.0");
+        // Lagging never downgrades
+        assert_eq!(policy(2).update_target(&metadata, "^1.2.0"), "1.2.0");
+        assert_eq!(policy(9).update_target(&metadata, "^1.0.0"), "1.0.0");
+    }
+
+    #[test]
+    fn trusts_latest_tag_missing_from_versions() {
+        let metadata = metadata(&["1.0.0", "1.1.0", "1.2.0"]);
+        assert_eq!(metadata.unlisted_latest(), Some("1.3.0"));
+        let in_range = UpdatePolicy {
+            in_range: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            UpdatePolicy::default().update_target(&metadata, "^1.0.0"),
+            "1.3.0"
+        );
+        assert_eq!(in_range.update_target(&metadata, "^1.0.0"), "1.3.0");
+        assert_eq!(
+            UpdatePolicy::default().level_targets(&metadata, "^1.1.0"),
+            ["1.3.0"]
+        );
+        assert!(is_published(&metadata, "1.3.0"));
+        assert!(!is_published(&metadata, "1.2.5"));
+    }
+
+    #[test]
+    fn parses_current_version_of_sloppy_versions() {
+        for declared in [" ^1.0.0", "v1.0.0", "=1.0.0"] {
+            assert_eq!(
+                current_version(declared),
+                Some(Version::parse("1.0.0").unwrap())
+            );
+        }
+    }
+}